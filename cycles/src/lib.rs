@@ -0,0 +1,102 @@
+//! Skipping ahead through deterministic processes, which must eventually repeat if they only have
+//! finitely many states.
+
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use tracing::debug;
+
+/// Where a process starts repeating itself.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Cycle {
+    /// The number of steps before the first state which is repeated.
+    pub start: usize,
+
+    /// The number of steps it takes to get back to that state.
+    pub len: usize,
+}
+
+impl Cycle {
+    /// The number of steps after which the state is the same as after `n` steps, but which is
+    /// less than `start + len`.
+    pub fn reduce(&self, n: usize) -> usize {
+        if n < self.start {
+            n
+        } else {
+            self.start + (n - self.start) % self.len
+        }
+    }
+}
+
+/// The state after applying `step` to `initial` `n` times.
+///
+/// Every state is remembered until one repeats, after which the remaining steps are skipped by
+/// going round the cycle, so `n` can be as large as you like as long as the cycle is short.
+pub fn fast_forward<S, F>(initial: S, n: usize, mut step: F) -> S
+where
+    S: Clone + Eq + Hash,
+    F: FnMut(&mut S),
+{
+    // the index of each state seen, and the states in order, so that `history[i]` is the state
+    // after `i` steps
+    let mut seen: HashMap<S, usize> = HashMap::new();
+    let mut history: Vec<S> = Vec::new();
+
+    let mut state = initial;
+
+    for i in 0..n {
+        if let Some(&start) = seen.get(&state) {
+            let cycle = Cycle {
+                start,
+                len: i - start,
+            };
+            debug!("found {cycle:?} after {i} steps");
+
+            return history.swap_remove(cycle.reduce(n));
+        }
+
+        seen.insert(state.clone(), i);
+        history.push(state.clone());
+
+        step(&mut state);
+    }
+
+    state
+}
+
+/// Applies `step` to `initial` until a state repeats, and returns where the cycle is.
+pub fn find_cycle<S, F>(initial: S, mut step: F) -> Cycle
+where
+    S: Clone + Eq + Hash,
+    F: FnMut(&mut S),
+{
+    let mut seen: HashMap<S, usize> = HashMap::new();
+
+    let mut state = initial;
+
+    for i in 0.. {
+        if let Some(start) = seen.insert(state.clone(), i) {
+            return Cycle {
+                start,
+                len: i - start,
+            };
+        }
+
+        step(&mut state);
+    }
+
+    unreachable!("the loop only ends by finding a cycle")
+}
+
+#[test]
+fn fast_forward_cycle() {
+    // 3, 10, 5, 16, 8, 4, 2, 1, 4, 2, 1, ...
+    let collatz = |n: &mut u64| *n = if *n & 1 == 0 { *n / 2 } else { 3 * *n + 1 };
+
+    assert_eq!(find_cycle(3, collatz), Cycle { start: 5, len: 3 });
+
+    assert_eq!(fast_forward(3, 0, collatz), 3);
+    assert_eq!(fast_forward(3, 4, collatz), 8);
+    assert_eq!(fast_forward(3, 7, collatz), 1);
+    assert_eq!(fast_forward(3, 1_000_000_000_000, collatz), 1);
+}
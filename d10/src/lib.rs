@@ -13,142 +13,268 @@ static PIPE_DIRECTIONS: phf::Map<char, [RookDirection; 2]> = phf::phf_map! {
     'F' => [RookDirection::PLUS_X, RookDirection::PLUS_Y],
 };
 
-fn uncover_start(mut grid: Grid<char>) -> (Grid<char>, GridPos) {
+/// Finds the pipe under `S` by trying every pipe there, and keeping the ones which make the start
+/// part of a closed loop. Fails unless exactly one does.
+fn uncover_start(grid: Grid<char>) -> anyhow::Result<Maze> {
     trace!("determining pipe under 'S'");
 
-    let (start_pos, _) = grid.iter_zm_with_pos().find(|(_, c)| **c == 'S').unwrap();
+    let (start_pos, _) = grid
+        .iter_zm_with_pos()
+        .find(|(_, c)| **c == 'S')
+        .context("maze should have a start")?;
 
-    let mut start_directions = RookDirection::iter().filter_map(|d| {
-        let neighbor = grid.get(start_pos + d)?;
-        trace!("  looking at neighbor {neighbor:?}");
-        let res = PIPE_DIRECTIONS
-            .get(neighbor)
-            .and_then(|directions| directions.contains(&-d).then_some(d));
-        trace!("  res = {res:?}");
-        res
-    });
+    let mut candidates = PIPE_DIRECTIONS
+        .keys()
+        .map(|&pipe| {
+            let mut grid = grid.clone();
+            grid[start_pos] = pipe;
 
-    let start_directions = [
-        start_directions.next().unwrap(),
-        start_directions.next().unwrap(),
-    ];
+            Maze { grid, start_pos }
+        })
+        .filter(|maze| {
+            let res = maze.trace_loop();
+            trace!("  S = {:?}: {res:?}", maze.grid[start_pos]);
+            res.is_ok()
+        })
+        .collect_vec();
 
-    let (&start_pipe, _) = PIPE_DIRECTIONS
-        .entries()
-        .find(|(_, v)| **v == start_directions)
-        .expect("directions should correspond to a pipe");
+    match candidates.len() {
+        0 => bail!("no pipe under 'S' at {start_pos} forms a closed loop"),
+        1 => {
+            let maze = candidates.pop().unwrap();
+            debug!("  S = {:?}", maze.grid[start_pos]);
+            Ok(maze)
+        }
+        _ => {
+            let pipes = candidates
+                .iter()
+                .map(|maze| maze.grid[start_pos])
+                .sorted()
+                .collect_vec();
+            bail!("'S' at {start_pos} could be any of {pipes:?}")
+        }
+    }
+}
 
-    grid[start_pos] = start_pipe;
+/// How to count the tiles enclosed by the loop.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum AreaMethod {
+    /// Scan each row, flipping between inside and outside whenever the loop is crossed.
+    #[default]
+    Scanline,
 
-    debug!("  S = {start_pipe:?}");
+    /// Find the loop's area with the shoelace formula, then its interior tiles with Pick's theorem.
+    Shoelace,
+}
 
-    (grid, start_pos)
+/// The pipe maze, with the pipe under `S` uncovered.
+#[derive(Clone, Debug)]
+pub struct Maze {
+    pub grid: Grid<char>,
+    pub start_pos: GridPos,
 }
 
-pub fn solve<Part: AocPart>(input: &str) -> usize {
-    let grid: Grid<char> = input.lines().map(|line| line.chars()).collect();
+impl FromStr for Maze {
+    type Err = anyhow::Error;
 
-    debug!("parsed grid:\n{}", grid.render(|_, c| *c));
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let grid: Grid<char> = s.lines().map(|line| line.chars()).collect();
 
-    let (grid, start_pos) = uncover_start(grid);
+        debug!("parsed grid:\n{}", grid.render(|_, c| *c));
 
-    let start_dirs = PIPE_DIRECTIONS[&grid[start_pos]];
+        uncover_start(grid)
+    }
+}
 
-    let mut current = [start_pos + start_dirs[0], start_pos + start_dirs[1]];
-    let mut prev_dir = [start_dirs[0], start_dirs[1]];
+impl Maze {
+    /// Every tile of the loop through the start, in order, beginning with the start.
+    pub fn trace_loop(&self) -> anyhow::Result<Vec<GridPos>> {
+        let mut tiles = vec![self.start_pos];
 
-    let mut loop_pipes: HashSet<GridPos> = HashSet::new();
-    loop_pipes.insert(start_pos);
+        let mut direction = PIPE_DIRECTIONS[&self.grid[self.start_pos]][0];
+        let mut pos = self.start_pos + direction;
 
-    for distance in 2.. {
-        for i in 0..2 {
-            loop_pipes.insert(current[i]);
+        while pos != self.start_pos {
+            let tile = self
+                .grid
+                .get(pos)
+                .with_context(|| format!("loop leaves the grid at {pos}"))?;
 
-            let next_dir = *PIPE_DIRECTIONS[&grid[current[i]]]
-                .iter()
-                .filter(|dir| **dir != -prev_dir[i])
-                .exactly_one()
-                .unwrap();
+            let &[a, b] = PIPE_DIRECTIONS
+                .get(tile)
+                .with_context(|| format!("loop runs into {tile:?} at {pos}"))?;
+
+            direction = if a == -direction {
+                b
+            } else if b == -direction {
+                a
+            } else {
+                bail!("{tile:?} at {pos} does not connect to the loop");
+            };
 
-            current[i] = current[i] + next_dir;
-            prev_dir[i] = next_dir;
+            tiles.push(pos);
+            pos = pos + direction;
         }
 
-        if current[0] == current[1] {
-            loop_pipes.insert(current[0]);
+        // the loop must come back in through the start's other end, not just pass through it
+        if PIPE_DIRECTIONS[&self.grid[self.start_pos]][1] != -direction {
+            bail!("loop does not close at {}", self.start_pos);
+        }
 
-            if Part::is_one() {
-                return distance;
-            } else {
-                break;
+        Ok(tiles)
+    }
+
+    /// The number of tiles enclosed by the loop.
+    pub fn enclosed_area(&self, method: AreaMethod) -> anyhow::Result<usize> {
+        match method {
+            AreaMethod::Scanline => {
+                let classes = self.classify()?;
+
+                debug!("\n{}", self.render(&classes));
+
+                Ok(classes
+                    .iter_zm_with_pos()
+                    .filter(|(_, class)| class.is_enclosed())
+                    .count())
             }
+            AreaMethod::Shoelace => Ok(shoelace_area(&self.trace_loop()?)),
         }
     }
 
-    debug!(
-        "\n{}",
-        grid.render(|p, c| if loop_pipes.contains(&p) { '#' } else { *c })
-    );
+    /// Classifies every tile by scanning each row, flipping between inside and outside whenever
+    /// the loop is crossed.
+    pub fn classify(&self) -> anyhow::Result<Grid<TileClass>> {
+        let loop_pipes: HashSet<GridPos> = self.trace_loop()?.into_iter().collect();
 
-    let mut area = 0;
+        let mut rows: Vec<Vec<TileClass>> = vec![Vec::new(); self.grid.height()];
 
-    let mut inside = false;
-    let mut entered = None;
+        let mut inside = false;
+        let mut entered = None;
 
-    for (pos, tile) in grid.iter_zm_with_pos() {
-        if loop_pipes.contains(&pos) {
-            match tile {
-                '|' => inside = !inside,
-                '-' => (),
-                'L' => entered = Some('L'),
-                'J' => {
-                    if entered.unwrap() == 'F' {
-                        inside = !inside
+        for (pos, tile) in self.grid.iter_zm_with_pos() {
+            let class = if loop_pipes.contains(&pos) {
+                match tile {
+                    '|' => inside = !inside,
+                    '-' => (),
+                    'L' => entered = Some('L'),
+                    'J' => {
+                        if entered == Some('F') {
+                            inside = !inside
+                        }
                     }
-                }
-                '7' => {
-                    if entered.unwrap() == 'L' {
-                        inside = !inside
+                    '7' => {
+                        if entered == Some('L') {
+                            inside = !inside
+                        }
                     }
+                    'F' => entered = Some('F'),
+                    other => bail!("{other:?} at {pos} is not a loop pipe"),
                 }
-                'F' => entered = Some('F'),
-                other => panic!("{other} at {pos} is not a loop pipe"),
+
+                trace!("{pos} {tile:?}, inside = {inside}");
+                TileClass::Loop
+            } else if PIPE_DIRECTIONS.contains_key(tile) {
+                TileClass::Junk { inside }
+            } else if inside {
+                TileClass::Inside
+            } else {
+                TileClass::Outside
+            };
+
+            rows[pos[1] as usize].push(class);
+        }
+
+        Ok(rows.into_iter().collect())
+    }
+
+    /// Draws the maze with the loop in box-drawing characters and enclosed tiles highlighted in
+    /// green, using ANSI escape codes.
+    pub fn render(&self, classes: &Grid<TileClass>) -> String {
+        let mut s = String::new();
+
+        for (pos, &tile) in self.grid.iter_zm_with_pos() {
+            if pos[0] == 0 && pos[1] != 0 {
+                s.push('\n');
             }
 
-            trace!("{pos} {tile:?}, inside = {inside}");
-        } else if inside {
-            trace!("found inside: {pos}");
-            area += 1;
+            match classes[pos] {
+                TileClass::Loop => s.push(box_drawing(tile)),
+                class if class.is_enclosed() => write!(s, "\x1b[42m{tile}\x1b[0m").unwrap(),
+                _ => s.push(tile),
+            }
         }
+
+        s
     }
+}
+
+/// Where a tile of the maze lies relative to the loop.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TileClass {
+    /// Part of the loop through the start.
+    Loop,
+
+    /// Ground enclosed by the loop.
+    Inside,
+
+    /// Ground outside the loop.
+    Outside,
 
-    area
+    /// A pipe which isn't part of the loop, and so can't be reached from the start.
+    Junk { inside: bool },
 }
 
-example_tests! {
-    - part one:
-        a00: ".....
-.S-7.
-.|.|.
-.L-J.
-....."=> 4,
-        a01: "-L|F7
-7S-7|
-L|7||
--L-J|
-L|-JF"=> 4,
-        a10: "..F7.
-.FJ|.
-SJ.L7
-|F--J
-LJ..." => 8,
-        a11: "7-F7-
-.FJ|7
-SJLL7
-|F--J
-LJ.LJ" => 8,
-    - part two:
-        b0: "...........
+impl TileClass {
+    /// Whether the tile counts towards the area enclosed by the loop.
+    pub fn is_enclosed(self) -> bool {
+        matches!(self, Self::Inside | Self::Junk { inside: true })
+    }
+}
+
+fn box_drawing(pipe: char) -> char {
+    match pipe {
+        '|' => '│',
+        '-' => '─',
+        'L' => '└',
+        'J' => '┘',
+        '7' => '┐',
+        'F' => '┌',
+        other => other,
+    }
+}
+
+/// The number of tiles strictly inside a loop of tiles, each adjacent to the next.
+fn shoelace_area(loop_tiles: &[GridPos]) -> usize {
+    // twice the area enclosed by the path through the centers of the tiles
+    let twice_area = loop_tiles
+        .iter()
+        .circular_tuple_windows()
+        .map(|(p, q)| p[0] * q[1] - q[0] * p[1])
+        .sum::<isize>()
+        .unsigned_abs();
+
+    // by Pick's theorem, area = interior + boundary / 2 - 1
+    (twice_area - loop_tiles.len()) / 2 + 1
+}
+
+pub fn solve_with<Part: AocPart>(input: &str, method: AreaMethod) -> usize {
+    let maze: Maze = input.parse().expect("maze should parse");
+
+    if Part::is_one() {
+        let loop_tiles = maze.trace_loop().expect("start should be on a loop");
+        return loop_tiles.len() / 2;
+    }
+
+    maze.enclosed_area(method)
+        .expect("start should be on a loop")
+}
+
+pub fn solve<Part: AocPart>(input: &str) -> usize {
+    solve_with::<Part>(input, AreaMethod::default())
+}
+
+#[cfg(test)]
+const EXAMPLE_B0: &str = "...........
 .S-------7.
 .|F-----7|.
 .||.....||.
@@ -156,8 +282,10 @@ LJ.LJ" => 8,
 .|L-7.F-J|.
 .|..|.|..|.
 .L--J.L--J.
-..........." => 4,
-        b1: ".F----7F7F7F7F-7....
+...........";
+
+#[cfg(test)]
+const EXAMPLE_B1: &str = ".F----7F7F7F7F-7....
 .|F--7||||||||FJ....
 .||.FJ||||||||L7....
 FJL7L7LJLJ||LJ.L-7..
@@ -166,8 +294,10 @@ L--J.L7...LJS7F-7L7.
 ....L7.F7||L7|.L7L7|
 .....|FJLJ|FJ|F7|.LJ
 ....FJL-7.||.||||...
-....L---J.LJ.LJLJ..." => 8,
-        b2: "FF7FSF7F7F7F7F7F---7
+....L---J.LJ.LJLJ...";
+
+#[cfg(test)]
+const EXAMPLE_B2: &str = "FF7FSF7F7F7F7F7F---7
 L|LJ||||||||||||F--J
 FL-7LJLJ||||||LJL-77
 F--JF--7||LJLJ7F7FJ-
@@ -176,7 +306,86 @@ L---JF-JLJ.||-FJLJJ7
 |FFJF7L7F-JF7|JL---7
 7-L-JL7||F7|L7F-7F7|
 L.L7LFJ|||||FJL7||LJ
-L7JLJL-JLJLJL--JLJ.L" => 10,
+L7JLJL-JLJLJL--JLJ.L";
+
+example_tests! {
+    - part one:
+        a00: ".....
+.S-7.
+.|.|.
+.L-J.
+....."=> 4,
+        a01: "-L|F7
+7S-7|
+L|7||
+-L-J|
+L|-JF"=> 4,
+        a10: "..F7.
+.FJ|.
+SJ.L7
+|F--J
+LJ..." => 8,
+        a11: "7-F7-
+.FJ|7
+SJLL7
+|F--J
+LJ.LJ" => 8,
+    - part two:
+        b0: EXAMPLE_B0 => 4,
+        b1: EXAMPLE_B1 => 8,
+        b2: EXAMPLE_B2 => 10,
+}
+
+#[test]
+fn area_methods_agree() {
+    for input in [EXAMPLE_B0, EXAMPLE_B1, EXAMPLE_B2] {
+        let maze: Maze = input.parse().unwrap();
+        assert_eq!(
+            maze.enclosed_area(AreaMethod::Scanline).unwrap(),
+            maze.enclosed_area(AreaMethod::Shoelace).unwrap(),
+        );
+    }
+}
+
+#[test]
+fn classification() {
+    let maze: Maze = "..F7.
+.FJ|.
+SJ.L7
+|F--J
+LJ...
+"
+    .parse()
+    .unwrap();
+
+    let classes = maze.classify().unwrap();
+    assert_eq!(classes[v!(0, 0)], TileClass::Outside);
+    assert_eq!(classes[v!(2, 2)], TileClass::Inside);
+    assert_eq!(classes[v!(0, 2)], TileClass::Loop);
+
+    assert_eq!(
+        maze.render(&classes),
+        "..┌┐.\n.┌┘│.\n┌┘\x1b[42m.\x1b[0m└┐\n│┌──┘\n└┘..."
+    );
+}
+
+#[test]
+fn start_inference() {
+    // both squares meeting at the start are closed loops
+    let ambiguous = "F-7..
+|.|..
+L-S-7
+..|.|
+..L-J";
+    let err = ambiguous.parse::<Maze>().unwrap_err();
+    assert!(err.to_string().ends_with("could be any of ['F', 'J']"));
+
+    let unambiguous = ambiguous.replace("L-S-7", "L-S.7");
+    let maze: Maze = unambiguous.parse().unwrap();
+    assert_eq!(maze.grid[maze.start_pos], 'J');
+
+    assert!("...\n.S.\n...".parse::<Maze>().is_err());
+    assert!("...".parse::<Maze>().is_err());
 }
 
 #[test]
@@ -4,67 +4,129 @@ const YEAR: usize = 2023;
 
 const DAY: usize = 11;
 
-fn solve<P: AocPart>(input: &str, expansion_factor: usize) -> usize {
+/// The position of every galaxy in the (unexpanded) image.
+pub fn galaxies(input: &str) -> Vec<GridPos> {
     let grid: Grid<char> = input.lines().map(|line| line.chars()).collect();
 
-    let mut galaxies: Vec<GridPos> = grid.positions_zm().filter(|p| grid[*p] == '#').collect();
+    grid.positions_zm().filter(|p| grid[*p] == '#').collect()
+}
 
-    debug!("galaxies: {galaxies:?}");
+/// Moves coordinates along one axis to where they end up once every line without a galaxy on it
+/// is `factor` lines wide instead of one.
+fn expand_axis(coords: &[usize], factor: usize) -> Vec<usize> {
+    let mut expanded = vec![0; coords.len()];
 
-    let expanding_columns: HashSet<usize> = (0..grid.dimension(0))
-        .filter(|x| galaxies.iter().all(|g| g[0] != *x as isize))
-        .collect();
-
-    let expanding_rows: HashSet<usize> = (0..grid.dimension(1))
-        .filter(|y| galaxies.iter().all(|g| g[1] != *y as isize))
-        .collect();
-
-    // debug!("expanding rows: {expanding_rows:?}");
-    debug!("expanding columns: {expanding_columns:?}");
-
-    let mut total_dist = 0;
-    while let Some(src_galaxy) = galaxies.pop() {
-        for (i, dst_galaxy) in galaxies.iter().enumerate() {
-            // find distance between src and dst in expanded universe
-
-            let mut x_dist = 0;
-            let src_x = cmp::min(src_galaxy[0], dst_galaxy[0]);
-            let dst_x = cmp::max(src_galaxy[0], dst_galaxy[0]);
-            for x in src_x..dst_x {
-                if expanding_columns.contains(&(x as usize)) {
-                    x_dist += expansion_factor;
-                } else {
-                    x_dist += 1;
-                }
-            }
-
-            let mut y_dist = 0;
-            let src_y = cmp::min(src_galaxy[1], dst_galaxy[1]);
-            let dst_y = cmp::max(src_galaxy[1], dst_galaxy[1]);
-            for y in src_y..dst_y {
-                if expanding_rows.contains(&(y as usize)) {
-                    y_dist += expansion_factor;
-                } else {
-                    y_dist += 1;
-                }
-            }
-
-            trace!(
-                "between galaxy {} and {}: {}",
-                i + 1,
-                galaxies.len() + 1,
-                x_dist + y_dist
-            );
-            total_dist += x_dist + y_dist;
+    // the number of distinct coordinates seen so far, which are the occupied lines below the
+    // current one
+    let mut occupied = 0;
+    let mut prev = None;
+    let mut empty = 0;
+
+    for i in (0..coords.len()).sorted_unstable_by_key(|&i| coords[i]) {
+        let coord = coords[i];
+
+        if prev != Some(coord) {
+            empty = coord - occupied;
+            occupied += 1;
+            prev = Some(coord);
         }
+
+        expanded[i] = coord - empty + empty * factor;
     }
 
-    total_dist
+    expanded
 }
 
-aoc_tests! {
-    inputs {
-        ex = "...#......
+/// The coordinates of every galaxy along each axis, in the same order as `galaxies`, after each
+/// empty column grows to `factors[0]` columns and each empty row grows to `factors[1]` rows.
+fn expanded_axes(galaxies: &[GridPos], factors: [usize; 2]) -> [Vec<usize>; 2] {
+    [0, 1].map(|axis| {
+        let coords = galaxies.iter().map(|g| g[axis] as usize).collect_vec();
+        let expanded = expand_axis(&coords, factors[axis]);
+
+        debug!("axis {axis} expanded: {expanded:?}");
+
+        expanded
+    })
+}
+
+/// The sum of the distances between every pair of coordinates along one axis.
+fn pairwise_distance_sum(mut coords: Vec<usize>) -> usize {
+    coords.sort_unstable();
+
+    let mut total = 0;
+    let mut below = 0;
+
+    for (i, coord) in coords.into_iter().enumerate() {
+        // this coordinate is at least as far along as the `i` coordinates before it
+        total += coord * i - below;
+        below += coord;
+    }
+
+    total
+}
+
+/// The sum of the shortest paths between every pair of galaxies, after each empty column grows
+/// to `factors[0]` columns and each empty row grows to `factors[1]` rows.
+pub fn distance_sum(galaxies: &[GridPos], factors: [usize; 2]) -> usize {
+    expanded_axes(galaxies, factors)
+        .into_iter()
+        .map(pairwise_distance_sum)
+        .sum()
+}
+
+/// Every galaxy's position once each empty column has grown to `factors[0]` columns and each empty
+/// row to `factors[1]` rows, in the same order as `galaxies`.
+pub fn expanded_galaxies(galaxies: &[GridPos], factors: [usize; 2]) -> Vec<GridPos> {
+    let [xs, ys] = expanded_axes(galaxies, factors);
+
+    xs.into_iter()
+        .zip(ys)
+        .map(|(x, y)| v!(x as isize, y as isize))
+        .collect()
+}
+
+/// The galaxies of an image after it has expanded, numbered by index in reading order.
+#[derive(Clone, Debug)]
+pub struct Universe {
+    pub galaxies: Vec<GridPos>,
+}
+
+impl Universe {
+    /// See [`expanded_galaxies`] for how `factors` apply.
+    pub fn new(input: &str, factors: [usize; 2]) -> Self {
+        Self {
+            galaxies: expanded_galaxies(&galaxies(input), factors),
+        }
+    }
+
+    /// The length of the shortest path between galaxies `a` and `b`.
+    pub fn distance(&self, a: usize, b: usize) -> usize {
+        let (a, b) = (self.galaxies[a], self.galaxies[b]);
+        (a[0] - b[0]).unsigned_abs() + (a[1] - b[1]).unsigned_abs()
+    }
+
+    /// The `k` galaxies closest to `galaxy` (not counting itself), nearest first, as pairs of
+    /// distance and galaxy. Ties are broken by galaxy number.
+    pub fn nearest(&self, galaxy: usize, k: usize) -> Vec<(usize, usize)> {
+        (0..self.galaxies.len())
+            .filter(|&other| other != galaxy)
+            .map(|other| (self.distance(galaxy, other), other))
+            .k_smallest(k)
+            .collect()
+    }
+}
+
+fn solve<P: AocPart>(input: &str, expansion_factor: usize) -> usize {
+    let galaxies = galaxies(input);
+
+    debug!("galaxies: {galaxies:?}");
+
+    distance_sum(&galaxies, [expansion_factor; 2])
+}
+
+#[cfg(test)]
+const EXAMPLE_INPUT: &str = "...#......
 .......#..
 #.........
 ..........
@@ -73,7 +135,11 @@ aoc_tests! {
 .........#
 ..........
 .......#..
-#...#.....",
+#...#.....";
+
+aoc_tests! {
+    inputs {
+        ex = EXAMPLE_INPUT,
     }
 
     part::One {
@@ -88,3 +154,37 @@ aoc_tests! {
         b: @input 1000000 => 504715068438,
     }
 }
+
+#[test]
+fn independent_factors() {
+    let galaxies = galaxies(EXAMPLE_INPUT);
+
+    assert_eq!(distance_sum(&galaxies, [1, 1]), 292);
+    assert_eq!(distance_sum(&galaxies, [2, 10]), 662);
+    assert_eq!(distance_sum(&galaxies, [10, 2]), 742);
+}
+
+#[test]
+fn expanded_universe() {
+    let universe = Universe::new(EXAMPLE_INPUT, [2, 2]);
+
+    assert_eq!(universe.galaxies[0], v!(4, 0));
+    assert_eq!(universe.galaxies[8], v!(5, 11));
+
+    assert_eq!(universe.distance(4, 8), 9);
+    assert_eq!(universe.distance(0, 6), 15);
+    assert_eq!(universe.distance(2, 5), 17);
+    assert_eq!(universe.distance(7, 8), 5);
+
+    assert_eq!(universe.nearest(0, 3), [(6, 1), (6, 2), (9, 3)]);
+    assert_eq!(universe.nearest(0, 20).len(), 8);
+
+    // the universe agrees with `distance_sum` when the axes grow differently
+    let universe = Universe::new(EXAMPLE_INPUT, [2, 10]);
+    let total: usize = (0..universe.galaxies.len())
+        .tuple_combinations()
+        .map(|(a, b)| universe.distance(a, b))
+        .sum();
+    assert_eq!(total, distance_sum(&galaxies(EXAMPLE_INPUT), [2, 10]));
+    assert_eq!(universe.galaxies[8], v!(5, 27));
+}
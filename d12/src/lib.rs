@@ -1,4 +1,11 @@
+mod nonogram;
+
 use aocutil::prelude::*;
+use num::bigint::RandBigInt;
+use num::{BigUint, CheckedAdd, One, ToPrimitive, Zero};
+use rand::Rng;
+
+pub use nonogram::{Cell, Nonogram};
 
 pub const YEAR: usize = 2023;
 
@@ -41,12 +48,16 @@ impl FromStr for Springs {
 }
 
 impl Springs {
-    fn unfold(self) -> Self {
-        let mut res = Self::default();
+    /// `factor` copies of the springs, separated by unknown springs. Panics if `factor` is zero.
+    fn unfold(self, factor: usize) -> Self {
+        assert!(factor > 0, "unfold factor should be positive");
 
-        res.len = (self.len * 5) + 4;
+        let mut res = Self {
+            len: (self.len + 1) * factor - 1,
+            ..Self::default()
+        };
 
-        for i in 0..5 {
+        for i in 0..factor {
             let offset = (self.len + 1) * i;
 
             for range in self.damaged.ranges() {
@@ -61,98 +72,380 @@ impl Springs {
         res
     }
 
-    #[instrument(level = "trace", ret)]
-    fn num_solutions(
+    /// The positions at which a run of `run` damaged springs could start, given that the runs
+    /// before it end before `start`.
+    fn placements(&self, start: usize, run: usize) -> impl Iterator<Item = usize> + '_ {
+        let last = self.len.checked_sub(run);
+
+        (start..=last.unwrap_or(0))
+            .filter(move |_| last.is_some())
+            // there cannot be another run before the match to this one
+            .take_while(move |&i| {
+                let passed = i != 0 && self.damaged.intersects(&Range::new(start, i - 1));
+                if passed {
+                    trace!("  invalid from {i}: passed unmatched broken range");
+                }
+                !passed
+            })
+            .filter(move |&i| {
+                let new_damaged_range = Range::new(i, i + run - 1);
+
+                trace!("looking at {new_damaged_range:?}");
+
+                // a run cannot contain a known operational spring
+                if self.operational.intersects(&new_damaged_range) {
+                    trace!("  invalid: intersects operational spring");
+                    return false;
+                }
+
+                // a run cannot abut a known broken spring, or it would be recorded incorrectly
+                if self.damaged.contains(&(new_damaged_range.end + 1)) {
+                    trace!("  invalid: abuts broken spring");
+                    return false;
+                }
+
+                trace!("valid");
+                true
+            })
+    }
+
+    /// The number of ways to place `runs[cur_run..]` from `start` on, or `None` if it doesn't fit
+    /// in a `C`.
+    #[instrument(level = "trace", skip(memory), ret)]
+    fn try_num_solutions<C>(
         &self,
         start: usize,
         cur_run: usize,
         runs: &[usize],
-        memory: &mut HashMap<(usize, usize), usize>,
-    ) -> usize {
+        memory: &mut HashMap<(usize, usize), C>,
+    ) -> Option<C>
+    where
+        C: Clone + Debug + Zero + One + CheckedAdd,
+    {
         if let Some(prev) = memory.get(&(start, cur_run)) {
-            return *prev;
+            return Some(prev.clone());
         }
 
         if cur_run >= runs.len() {
             if self.damaged.intersects(&(start..=self.len)) {
                 trace!("ran out of runs with unmatched left; 0 solutions");
-                return 0;
+                return Some(C::zero());
             } else {
                 trace!("no more runs; 1 solution");
-                return 1;
+                return Some(C::one());
             }
         }
 
-        let first_run = runs[cur_run];
+        let run = runs[cur_run];
 
-        let mut res = 0;
+        let mut res = C::zero();
 
-        for i in start..=(self.len - first_run) {
-            let new_damaged_range = Range::new(i, i + first_run - 1);
+        for i in self.placements(start, run).collect_vec() {
+            let after = self.try_num_solutions(i + run + 1, cur_run + 1, runs, memory)?;
+            res = res.checked_add(&after)?;
+        }
 
-            trace!("looking at {new_damaged_range:?}");
+        memory.insert((start, cur_run), res.clone());
 
-            // a run cannot contain a known operational spring
-            if self.operational.intersects(&new_damaged_range) {
-                trace!("  invalid: intersects operational spring");
-                continue;
-            }
+        Some(res)
+    }
+
+    /// Like [`Self::try_num_solutions`], but in arbitrary precision, so it never overflows.
+    fn num_solutions(
+        &self,
+        start: usize,
+        cur_run: usize,
+        runs: &[usize],
+        memory: &mut HashMap<(usize, usize), BigUint>,
+    ) -> BigUint {
+        self.try_num_solutions(start, cur_run, runs, memory)
+            .expect("arbitrary precision counts should not overflow")
+    }
+
+    fn is_unknown(&self, i: usize) -> bool {
+        !self.damaged.contains(&i) && !self.operational.contains(&i)
+    }
+}
+
+/// One line of the condition records: some springs, and the lengths of the runs of damaged
+/// springs among them.
+#[derive(Clone, Debug)]
+pub struct Record {
+    springs: Springs,
+    runs: Vec<usize>,
+}
+
+impl FromStr for Record {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (springs, runs) = s.split_once(' ').context("record should have a space")?;
 
-            // a run cannot abut a known broken spring, or it would be recorded incorrectly
-            if self.damaged.contains(&(new_damaged_range.end + 1)) {
-                trace!("  invalid: abuts broken spring");
+        Ok(Self {
+            springs: springs.parse()?,
+            runs: runs
+                .split(',')
+                .map(|r| r.parse::<usize>().context("runs should be numbers"))
+                .collect::<anyhow::Result<_>>()?,
+        })
+    }
+}
+
+impl Record {
+    /// `factor` copies of the record, with the copies of the springs separated by unknown
+    /// springs. Panics if `factor` is zero.
+    pub fn unfold(self, factor: usize) -> Self {
+        let runs_len = self.runs.len();
+
+        Self {
+            springs: self.springs.unfold(factor),
+            runs: self
+                .runs
+                .into_iter()
+                .cycle()
+                .take(runs_len * factor)
+                .collect(),
+        }
+    }
+
+    /// The number of arrangements of damaged springs which fit the record. Counts in a `usize`
+    /// where possible, only falling back to arbitrary precision if that would overflow.
+    pub fn count(&self) -> BigUint {
+        let small = self
+            .springs
+            .try_num_solutions::<usize>(0, 0, &self.runs, &mut HashMap::new());
+
+        small.map(BigUint::from).unwrap_or_else(|| {
+            debug!("count overflowed a usize; counting again with arbitrary precision");
+
+            self.springs
+                .num_solutions(0, 0, &self.runs, &mut HashMap::new())
+        })
+    }
+
+    fn render(&self, starts: &[usize]) -> String {
+        let mut springs = vec!['.'; self.springs.len];
+
+        for (&start, &run) in starts.iter().zip(&self.runs) {
+            springs[start..start + run].fill('#');
+        }
+
+        springs.into_iter().collect()
+    }
+
+    /// Every arrangement which fits the record, with each unknown spring filled in.
+    pub fn arrangements(&self) -> Arrangements<'_> {
+        Arrangements::new(self)
+    }
+
+    /// An arrangement chosen uniformly at random from the ones which fit the record, or `None` if
+    /// none do.
+    pub fn sample(&self, rng: &mut impl Rng) -> Option<String> {
+        let mut memory = HashMap::new();
+
+        if self
+            .springs
+            .num_solutions(0, 0, &self.runs, &mut memory)
+            .is_zero()
+        {
+            return None;
+        }
+
+        let mut starts = Vec::new();
+        let mut start = 0;
+
+        for (cur_run, &run) in self.runs.iter().enumerate() {
+            // weight each placement by the number of ways to finish the arrangement after it
+            let weighted = self
+                .springs
+                .placements(start, run)
+                .collect_vec()
+                .into_iter()
+                .map(|i| {
+                    let after = self.springs.num_solutions(
+                        i + run + 1,
+                        cur_run + 1,
+                        &self.runs,
+                        &mut memory,
+                    );
+                    (i, after)
+                })
+                .collect_vec();
+
+            let total: BigUint = weighted.iter().map(|(_, after)| after).sum();
+            let mut choice = rng.gen_biguint_below(&total);
+
+            let (i, _) = weighted
+                .into_iter()
+                .find(|(_, after)| {
+                    if choice < *after {
+                        true
+                    } else {
+                        choice -= after;
+                        false
+                    }
+                })
+                .expect("choice should be less than the total");
+
+            starts.push(i);
+            start = i + run + 1;
+        }
+
+        Some(self.render(&starts))
+    }
+
+    /// For each unknown spring in order, its position and the number of arrangements in which it
+    /// is damaged.
+    pub fn damaged_counts(&self) -> Vec<(usize, BigUint)> {
+        let runs = &self.runs;
+
+        let mut memory = HashMap::new();
+        let mut damaged = vec![BigUint::zero(); self.springs.len];
+
+        // the number of ways to reach each state. every placement moves to a later start, so
+        // taking the earliest state first means all the ways into it have been counted.
+        let mut ways: BTreeMap<(usize, usize), BigUint> =
+            BTreeMap::from([((0, 0), BigUint::one())]);
+
+        while let Some(((start, cur_run), ways_here)) = ways.pop_first() {
+            let Some(&run) = runs.get(cur_run) else {
                 continue;
-            }
+            };
 
-            // there cannot be another run before the match to the first one
-            if new_damaged_range.start != 0
-                && self
-                    .damaged
-                    .intersects(&Range::new(start, new_damaged_range.start - 1))
-            {
-                trace!("  invalid: passed unmatched broken range");
-                break;
-            }
+            for i in self.springs.placements(start, run).collect_vec() {
+                let next = (i + run + 1, cur_run + 1);
 
-            trace!("valid");
+                let after = self
+                    .springs
+                    .num_solutions(next.0, next.1, runs, &mut memory);
+                if after.is_zero() {
+                    continue;
+                }
 
-            res += self.num_solutions(new_damaged_range.end + 2, cur_run + 1, runs, memory);
+                let through = &ways_here * after;
+                for count in &mut damaged[i..i + run] {
+                    *count += &through;
+                }
+
+                *ways.entry(next).or_default() += &ways_here;
+            }
         }
 
-        memory.insert((start, cur_run), res);
+        (0..self.springs.len)
+            .filter(|&i| self.springs.is_unknown(i))
+            .map(|i| (i, damaged[i].clone()))
+            .collect()
+    }
+}
+
+/// Lazily enumerates the arrangements which fit a [`Record`], in order of where their runs start.
+pub struct Arrangements<'a> {
+    record: &'a Record,
+
+    memory: HashMap<(usize, usize), BigUint>,
+
+    /// For each run being placed, the starts left to try which lead to at least one arrangement.
+    stack: Vec<std::vec::IntoIter<usize>>,
+
+    /// The starts of the runs placed so far.
+    starts: Vec<usize>,
+
+    /// Whether the record has no runs, and the single arrangement without any is still to come.
+    pending_empty: bool,
+}
+
+impl<'a> Arrangements<'a> {
+    fn new(record: &'a Record) -> Self {
+        let mut res = Self {
+            record,
+            memory: HashMap::new(),
+            stack: Vec::new(),
+            starts: Vec::new(),
+            pending_empty: false,
+        };
+
+        if record.runs.is_empty() {
+            res.pending_empty = record.count().is_one();
+        } else {
+            let options = res.viable(0, 0);
+            res.stack.push(options);
+        }
 
         res
     }
+
+    /// The starts for run `cur_run` after `start` which can be finished into an arrangement.
+    fn viable(&mut self, start: usize, cur_run: usize) -> std::vec::IntoIter<usize> {
+        let Self { record, memory, .. } = self;
+        let run = record.runs[cur_run];
+
+        record
+            .springs
+            .placements(start, run)
+            .collect_vec()
+            .into_iter()
+            .filter(|&i| {
+                !record
+                    .springs
+                    .num_solutions(i + run + 1, cur_run + 1, &record.runs, memory)
+                    .is_zero()
+            })
+            .collect_vec()
+            .into_iter()
+    }
 }
 
-pub fn solve<Part: AocPart>(input: &str) -> usize {
-    input
-        .lines()
-        .map(|line| {
-            debug!("done");
-            let (springs, runs) = line.split_once(' ').expect("space in input");
+impl Iterator for Arrangements<'_> {
+    type Item = String;
 
-            let mut springs = springs.parse::<Springs>().expect("valid springs");
+    fn next(&mut self) -> Option<Self::Item> {
+        if std::mem::take(&mut self.pending_empty) {
+            return Some(self.record.render(&[]));
+        }
 
-            let mut runs: Vec<usize> = runs
-                .split(',')
-                .map(|r| r.parse::<usize>().expect("runs are numbers"))
-                .collect();
+        loop {
+            let cur_run = self.stack.len().checked_sub(1)?;
 
-            if Part::is_two() {
-                springs = springs.unfold();
+            let Some(i) = self.stack[cur_run].next() else {
+                self.stack.pop();
+                continue;
+            };
+
+            self.starts.truncate(cur_run);
+            self.starts.push(i);
 
-                let runs_len = runs.len();
-                runs = runs.into_iter().cycle().take(runs_len * 5).collect();
+            if cur_run + 1 == self.record.runs.len() {
+                return Some(self.record.render(&self.starts));
             }
 
-            let mut memory = HashMap::new();
+            let options = self.viable(i + self.record.runs[cur_run] + 1, cur_run + 1);
+            self.stack.push(options);
+        }
+    }
+}
+
+/// Sums the number of arrangements of each record, after unfolding it into `unfold_factor`
+/// copies.
+pub fn solve_with_factor(input: &str, unfold_factor: usize) -> BigUint {
+    input
+        .lines()
+        .map(|line| {
+            debug!("done");
+            let record = line.parse::<Record>().expect("valid record");
 
-            springs.num_solutions(0, 0, &runs, &mut memory)
+            record.unfold(unfold_factor).count()
         })
         .sum()
 }
 
+pub fn solve<Part: AocPart>(input: &str) -> usize {
+    let unfold_factor = if Part::is_one() { 1 } else { 5 };
+
+    solve_with_factor(input, unfold_factor)
+        .to_usize()
+        .expect("answer should fit in a usize")
+}
+
 aoc_tests! {
     inputs {
         e0 = "???.### 1,1,3
@@ -173,3 +466,56 @@ aoc_tests! {
         rb: @input => 10153896718999,
     }
 }
+
+#[test]
+fn arrangements() {
+    let record: Record = "?###???????? 3,2,1".parse().unwrap();
+
+    let all = record.arrangements().collect_vec();
+    assert_eq!(BigUint::from(all.len()), record.count());
+    assert_eq!(all.len(), 10);
+    assert_eq!(all[0], ".###.##.#...");
+    assert!(all.iter().all_unique());
+
+    use rand::SeedableRng;
+    let mut rng = rand::rngs::StdRng::seed_from_u64(12);
+    for _ in 0..20 {
+        assert!(all.contains(&record.sample(&mut rng).unwrap()));
+    }
+
+    let record: Record = ".??..??...?##. 1,1,3".parse().unwrap();
+    assert_eq!(
+        record.damaged_counts(),
+        [(1, 2u32), (2, 2), (5, 2), (6, 2), (10, 4)].map(|(i, count)| (i, BigUint::from(count)))
+    );
+
+    let impossible: Record = "#.# 1".parse().unwrap();
+    assert_eq!(impossible.arrangements().count(), 0);
+    assert_eq!(impossible.sample(&mut rng), None);
+}
+
+#[test]
+fn large_unfold_factors() {
+    use rand::SeedableRng;
+
+    let record: Record = ".??..??...?##. 1,1,3".parse().unwrap();
+
+    // each copy after the first has 8 arrangements, as the unknown spring joining it on can be
+    // damaged
+    assert_eq!(record.clone().unfold(5).count(), BigUint::from(16384u32));
+    let unfolded = record.unfold(50);
+    assert_eq!(
+        unfolded.count(),
+        BigUint::from(4u32) * BigUint::from(8u32).pow(49)
+    );
+
+    // too many arrangements to count in a `usize`, but they can still be walked and sampled
+    let first = unfolded.arrangements().next().unwrap();
+    assert!(first.starts_with(".#...#....###.#.#........###."));
+
+    let mut rng = rand::rngs::StdRng::seed_from_u64(50);
+    assert_eq!(unfolded.sample(&mut rng).unwrap().len(), first.len());
+
+    let damaged_counts = unfolded.damaged_counts();
+    assert_eq!(damaged_counts.last().unwrap().1, unfolded.count());
+}
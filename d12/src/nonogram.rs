@@ -0,0 +1,241 @@
+use super::*;
+
+/// The state of one cell of a nonogram.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Cell {
+    Unknown,
+    Filled,
+    Empty,
+}
+
+impl Cell {
+    fn to_char(self) -> char {
+        match self {
+            Cell::Unknown => '?',
+            Cell::Filled => '#',
+            Cell::Empty => '.',
+        }
+    }
+}
+
+/// A 2D nonogram: the lengths of the runs of filled cells in every row and column.
+///
+/// Each row and column is a spring record of its own, with filled cells for damaged springs, so
+/// lines are solved with the same counting as the puzzle.
+#[derive(Clone, Debug)]
+pub struct Nonogram {
+    /// Top to bottom.
+    pub rows: Vec<Vec<usize>>,
+
+    /// Left to right.
+    pub cols: Vec<Vec<usize>>,
+}
+
+/// Reads the clues for the rows, then a blank line, then the clues for the columns. Each clue is
+/// a line of run lengths separated by spaces or commas; a line without any runs is written `0`.
+impl FromStr for Nonogram {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (rows, cols) = s
+            .trim()
+            .split_once("\n\n")
+            .context("clues should have a blank line between rows and columns")?;
+
+        let parse_section = |section: &str| -> Vec<Vec<usize>> {
+            section
+                .lines()
+                .map(|line| ints(line).filter(|&run| run != 0).collect())
+                .collect()
+        };
+
+        let (rows, cols) = (parse_section(rows), parse_section(cols));
+
+        let filled: [usize; 2] = [&rows, &cols].map(|lines| lines.iter().flatten().sum());
+        if filled[0] != filled[1] {
+            bail!(
+                "rows fill {} cells, but columns fill {}",
+                filled[0],
+                filled[1]
+            );
+        }
+
+        Ok(Self { rows, cols })
+    }
+}
+
+impl Nonogram {
+    pub fn width(&self) -> usize {
+        self.cols.len()
+    }
+
+    pub fn height(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// A grid of the right size, with nothing known.
+    pub fn blank(&self) -> Grid<Cell> {
+        (0..self.height())
+            .map(|_| iter::repeat_n(Cell::Unknown, self.width()))
+            .collect()
+    }
+
+    pub fn render(grid: &Grid<Cell>) -> String {
+        grid.render(|_, cell| cell.to_char())
+    }
+
+    /// Every row then every column, as the positions of its cells and its runs.
+    fn lines(&self) -> impl Iterator<Item = (Vec<GridPos>, &[usize])> {
+        let rows = self.rows.iter().enumerate().map(|(y, runs)| {
+            let line = (0..self.width())
+                .map(|x| v!(x as isize, y as isize))
+                .collect_vec();
+            (line, runs.as_slice())
+        });
+
+        let cols = self.cols.iter().enumerate().map(|(x, runs)| {
+            let line = (0..self.height())
+                .map(|y| v!(x as isize, y as isize))
+                .collect_vec();
+            (line, runs.as_slice())
+        });
+
+        rows.chain(cols)
+    }
+
+    /// Fills in every cell of `grid` which is the same in all the arrangements of its row or
+    /// column, until nothing changes. Returns `false` if some line has no arrangements left.
+    pub fn propagate(&self, grid: &mut Grid<Cell>) -> bool {
+        loop {
+            let mut changed = false;
+
+            for (line, runs) in self.lines() {
+                let cells = line.iter().map(|&pos| grid[pos]).collect_vec();
+
+                let Some(settled) = settle_line(&cells, runs) else {
+                    trace!("no arrangements for {line:?}");
+                    return false;
+                };
+
+                for (&pos, cell) in line.iter().zip(settled) {
+                    if grid[pos] != cell {
+                        grid[pos] = cell;
+                        changed = true;
+                    }
+                }
+            }
+
+            if !changed {
+                return true;
+            }
+        }
+    }
+
+    /// Propagates, then guesses the first unknown cell either way, collecting solutions until
+    /// there are `limit` of them.
+    fn search(&self, mut grid: Grid<Cell>, limit: usize, solutions: &mut Vec<Grid<Cell>>) {
+        if solutions.len() >= limit || !self.propagate(&mut grid) {
+            return;
+        }
+
+        let Some((guess, _)) = grid
+            .iter_zm_with_pos()
+            .find(|(_, &cell)| cell == Cell::Unknown)
+        else {
+            debug!("found solution:\n{}", Self::render(&grid));
+            solutions.push(grid);
+            return;
+        };
+
+        for cell in [Cell::Filled, Cell::Empty] {
+            trace!("guessing {cell:?} at {guess}");
+
+            let mut guessed = grid.clone();
+            guessed[guess] = cell;
+            self.search(guessed, limit, solutions);
+        }
+    }
+
+    /// Some solution, if there is one.
+    pub fn solve(&self) -> Option<Grid<Cell>> {
+        let mut solutions = Vec::new();
+        self.search(self.blank(), 1, &mut solutions);
+        solutions.pop()
+    }
+
+    /// The number of solutions. Every solution is visited, so this is only for small puzzles.
+    pub fn count_solutions(&self) -> usize {
+        let mut solutions = Vec::new();
+        self.search(self.blank(), usize::MAX, &mut solutions);
+        solutions.len()
+    }
+}
+
+/// The cells of one line, with any which are the same in all its arrangements filled in, or
+/// `None` if it has no arrangements.
+fn settle_line(cells: &[Cell], runs: &[usize]) -> Option<Vec<Cell>> {
+    let record = Record {
+        springs: cells
+            .iter()
+            .map(|cell| cell.to_char())
+            .collect::<String>()
+            .parse()
+            .expect("springs should always parse"),
+        runs: runs.to_vec(),
+    };
+
+    let total = record
+        .springs
+        .num_solutions(0, 0, &record.runs, &mut HashMap::new());
+    if total.is_zero() {
+        return None;
+    }
+
+    let mut settled = cells.to_vec();
+
+    for (i, damaged) in record.damaged_counts() {
+        if damaged == total {
+            settled[i] = Cell::Filled;
+        } else if damaged.is_zero() {
+            settled[i] = Cell::Empty;
+        }
+    }
+
+    Some(settled)
+}
+
+#[test]
+fn solve_nonograms() {
+    let letter: Nonogram = "3
+1 1
+5
+1 1
+1 1
+
+4
+1,1
+1,1
+1,1
+4"
+    .parse()
+    .unwrap();
+
+    let solution = letter.solve().unwrap();
+    assert_eq!(
+        Nonogram::render(&solution),
+        ".###.\n#...#\n#####\n#...#\n#...#"
+    );
+    assert_eq!(letter.count_solutions(), 1);
+
+    // the two diagonals can't be told apart without guessing
+    let diagonal: Nonogram = "1\n1\n\n1\n1".parse().unwrap();
+    let mut grid = diagonal.blank();
+    assert!(diagonal.propagate(&mut grid));
+    assert_eq!(Nonogram::render(&grid), "??\n??");
+    assert_eq!(diagonal.count_solutions(), 2);
+
+    assert!("2\n0\n\n1\n0".parse::<Nonogram>().is_err());
+
+    let contradiction: Nonogram = "2\n0\n\n0\n2".parse().unwrap();
+    assert!(contradiction.solve().is_none());
+}
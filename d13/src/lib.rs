@@ -4,65 +4,181 @@ pub const YEAR: usize = 2023;
 
 pub const DAY: usize = 13;
 
-fn one_different<'a>(
-    lhs: impl IntoIterator<Item = &'a [char]>,
-    rhs: impl IntoIterator<Item = &'a [char]>,
-) -> bool {
-    let mut differences = 0;
-
-    for (l, r) in iter::zip(lhs, rhs) {
-        for (l, r) in iter::zip(l, r) {
-            if l != r {
-                differences += 1;
-            }
+/// Which way a line of reflection runs.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Axis {
+    /// Between two rows.
+    Horizontal,
+
+    /// Between two columns.
+    Vertical,
+}
+
+/// A line of reflection, along with the smudges which spoil it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Reflection {
+    pub axis: Axis,
+
+    /// The greater of the two indices flanking the line.
+    pub index: usize,
+
+    /// For each pair of mirrored cells which differ, the position of the one before the line.
+    pub smudges: Vec<GridPos>,
+}
+
+impl Reflection {
+    /// The number that the puzzle summarizes this line with.
+    pub fn summary(&self) -> usize {
+        match self.axis {
+            Axis::Horizontal => 100 * self.index,
+            Axis::Vertical => self.index,
         }
     }
+}
 
-    differences == 1
+/// A pattern packed into bits, with bit `x` of row `y` and bit `y` of column `x` set if there is
+/// rock at `(x, y)`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Pattern {
+    rows: Vec<u128>,
+    cols: Vec<u128>,
 }
 
-/// Returns the greater of the two indices flanking the line of reflection.
-fn index_of_reflection<P: AocPart>(pattern: &Grid<char>) -> Option<usize> {
-    for i in 1..pattern.height() {
-        let mut l: Vec<&[char]> = pattern.rows().take(i).collect();
-        l.reverse();
+impl Pattern {
+    /// The widest or tallest pattern that can be packed.
+    pub const MAX_SIZE: usize = u128::BITS as usize;
+
+    pub fn from_grid(grid: &Grid<char>) -> anyhow::Result<Self> {
+        if grid.width() > Self::MAX_SIZE || grid.height() > Self::MAX_SIZE {
+            bail!(
+                "{}x{} pattern is larger than {max}x{max}",
+                grid.width(),
+                grid.height(),
+                max = Self::MAX_SIZE
+            );
+        }
+
+        let mut rows = vec![0; grid.height()];
+        let mut cols = vec![0; grid.width()];
 
-        let r: Vec<&[char]> = pattern.rows().skip(i).collect();
+        for (pos, &c) in grid.iter_zm_with_pos() {
+            let (x, y) = (pos[0] as usize, pos[1] as usize);
 
-        if match P::part() {
-            Part::One => iter::zip(l, r).all(|(l, r)| l == r),
-            Part::Two => one_different(l, r),
-        } {
-            return Some(i);
+            match c {
+                '#' => {
+                    rows[y] |= 1 << x;
+                    cols[x] |= 1 << y;
+                }
+                '.' => (),
+                other => bail!("unknown cell {other:?} at {pos}"),
+            }
         }
-    }
 
-    None
+        Ok(Self { rows, cols })
+    }
 }
 
-fn reflection_line<P: AocPart>(pattern: Grid<char>) -> usize {
-    if let Some(horizontal_line) = index_of_reflection::<P>(&pattern) {
-        100 * horizontal_line
-    } else {
-        index_of_reflection::<P>(&pattern.transposed())
-            .expect("there should be a line of reflection")
+impl FromStr for Pattern {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let grid: Grid<char> = s.lines().map(str::chars).collect();
+
+        Self::from_grid(&grid)
     }
 }
 
+/// Every line between two of `lines` which makes a reflection with exactly `smudges` smudges, as
+/// pairs of the line's index and the smudges' positions, given as the index of the line before
+/// the reflection and the bit within it.
+fn line_reflections(lines: &[u128], smudges: usize) -> Vec<(usize, Vec<(usize, usize)>)> {
+    (1..lines.len())
+        .filter_map(|i| {
+            let pairs = iter::zip((0..i).rev(), i..lines.len());
+
+            let mut differences = 0;
+            for (a, b) in pairs.clone() {
+                differences += (lines[a] ^ lines[b]).count_ones() as usize;
+
+                if differences > smudges {
+                    return None;
+                }
+            }
+
+            if differences != smudges {
+                return None;
+            }
+
+            let found = pairs
+                .flat_map(|(a, b)| {
+                    let mut diff = lines[a] ^ lines[b];
+
+                    iter::from_fn(move || {
+                        let bit = diff.trailing_zeros() as usize;
+                        diff &= diff.checked_sub(1)?;
+                        Some((a, bit))
+                    })
+                })
+                .collect();
+
+            Some((i, found))
+        })
+        .collect()
+}
+
+/// Every line of reflection in `pattern` which has exactly `smudges` smudges, horizontal ones
+/// first.
+pub fn reflections(pattern: &Pattern, smudges: usize) -> Vec<Reflection> {
+    let horizontal =
+        line_reflections(&pattern.rows, smudges)
+            .into_iter()
+            .map(|(index, smudges)| Reflection {
+                axis: Axis::Horizontal,
+                index,
+                smudges: smudges
+                    .into_iter()
+                    .map(|(y, x)| v!(x as isize, y as isize))
+                    .collect(),
+            });
+
+    let vertical = line_reflections(&pattern.cols, smudges)
+        .into_iter()
+        .map(|(index, smudges)| Reflection {
+            axis: Axis::Vertical,
+            index,
+            smudges: smudges
+                .into_iter()
+                .map(|(x, y)| v!(x as isize, y as isize))
+                .collect(),
+        });
+
+    horizontal.chain(vertical).collect()
+}
+
 pub fn solve<P: AocPart>(input: &str) -> usize {
+    let smudges = match P::part() {
+        Part::One => 0,
+        Part::Two => 1,
+    };
+
     input
         .split("\n\n")
         .map(|pattern| {
-            let pattern: Grid<char> = pattern.lines().map(str::chars).collect();
+            let pattern: Pattern = pattern.parse().expect("pattern should parse");
+
+            let reflections = reflections(&pattern, smudges);
+            trace!("reflections: {reflections:?}");
 
-            reflection_line::<P>(pattern)
+            reflections
+                .first()
+                .expect("there should be a line of reflection")
+                .summary()
         })
         .sum()
 }
 
-aoc_tests! {
-    inputs {
-        e0 = "#.##..##.
+#[cfg(test)]
+const EXAMPLE_INPUT: &str = "#.##..##.
 ..#.##.#.
 ##......#
 ##......#
@@ -76,7 +192,11 @@ aoc_tests! {
 #####.##.
 #####.##.
 ..##..###
-#....#..#",
+#....#..#";
+
+aoc_tests! {
+    inputs {
+        e0 = EXAMPLE_INPUT,
     }
 
     part::One {
@@ -89,3 +209,43 @@ aoc_tests! {
         rb: @input => 34224,
     }
 }
+
+#[test]
+fn smudged_reflections() {
+    let patterns = EXAMPLE_INPUT
+        .split("\n\n")
+        .map(|pattern| pattern.parse::<Pattern>().unwrap())
+        .collect_vec();
+
+    let exact = reflections(&patterns[0], 0);
+    assert_eq!(exact.len(), 1);
+    assert_eq!((exact[0].axis, exact[0].index), (Axis::Vertical, 5));
+
+    assert_eq!(
+        reflections(&patterns[0], 1),
+        [Reflection {
+            axis: Axis::Horizontal,
+            index: 3,
+            smudges: vec![v!(0, 0)],
+        }]
+    );
+
+    assert_eq!(
+        reflections(&patterns[1], 1),
+        [Reflection {
+            axis: Axis::Horizontal,
+            index: 1,
+            smudges: vec![v!(4, 0)],
+        }]
+    );
+
+    let exact = reflections(&patterns[1], 0);
+    assert_eq!((exact[0].axis, exact[0].index), (Axis::Horizontal, 4));
+
+    let wide = "#".repeat(Pattern::MAX_SIZE + 1);
+    assert!(wide.parse::<Pattern>().is_err());
+
+    for reflection in reflections(&patterns[1], 2) {
+        assert_eq!(reflection.smudges.len(), 2);
+    }
+}
@@ -0,0 +1,76 @@
+use aocutil::prelude::*;
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use d14::{Platform, DAY, SPIN_CYCLE, YEAR};
+
+/// The platform as it used to be stored, as a set of rounded rock positions, kept here to compare
+/// against.
+fn hash_set_tilt(grid: &Grid<char>, round_rocks: &mut HashSet<GridPos>, direction: RookDirection) {
+    for i in 0..grid.dimension(direction.perpendicular_axis()) {
+        let fall_axis_dimension = grid.dimension(direction.axis()) as isize;
+
+        let mut falling_point = match direction.sign() {
+            LineDirection::Negative => 0,
+            LineDirection::Positive => fall_axis_dimension - 1,
+        };
+
+        let range: Box<dyn Iterator<Item = isize>> = match direction.sign() {
+            LineDirection::Negative => Box::new(0..fall_axis_dimension),
+            LineDirection::Positive => Box::new((0..fall_axis_dimension).rev()),
+        };
+
+        for j in range {
+            let mut pos = v!(0, 0);
+            pos[direction.axis()] = j;
+            pos[direction.perpendicular_axis()] = i as isize;
+
+            if round_rocks.remove(&pos) {
+                pos[direction.axis()] = falling_point;
+                round_rocks.insert(pos);
+                falling_point -= direction.sign().to_num::<isize>();
+            } else if grid[pos] == '#' {
+                falling_point = j - direction.sign().to_num::<isize>();
+            }
+        }
+    }
+}
+
+fn spin_cycles(c: &mut Criterion) {
+    let input = aocutil::get_input(YEAR, DAY);
+
+    let mut group = c.benchmark_group("100 spin cycles");
+
+    group.bench_function("hash set", |b| {
+        let grid: Grid<char> = input.lines().map(str::chars).collect();
+        let round_rocks: HashSet<GridPos> = grid
+            .positions_zm()
+            .filter(|&pos| grid[pos] == 'O')
+            .collect();
+
+        b.iter(|| {
+            let mut round_rocks = round_rocks.clone();
+            for _ in 0..100 {
+                for direction in SPIN_CYCLE {
+                    hash_set_tilt(&grid, &mut round_rocks, direction);
+                }
+            }
+            black_box(round_rocks)
+        })
+    });
+
+    group.bench_function("bitboard", |b| {
+        let (platform, rocks) = Platform::parse(&input).unwrap();
+
+        b.iter(|| {
+            let mut rocks = rocks.clone();
+            for _ in 0..100 {
+                platform.spin_cycle(&mut rocks);
+            }
+            black_box(rocks)
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, spin_cycles);
+criterion_main!(benches);
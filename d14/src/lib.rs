@@ -1,115 +1,236 @@
+mod program;
+
 use aocutil::prelude::*;
 
+pub use program::{TiltCommand, TiltProgram};
+
 pub const YEAR: usize = 2023;
 
 pub const DAY: usize = 14;
 
-fn tilt_platform(grid: &Grid<char>, round_rocks: &mut HashSet<GridPos>, direction: RookDirection) {
-    for i in 0..grid.dimension(direction.perpendicular_axis()) {
-        let fall_axis_dimension = grid.dimension(direction.axis()) as isize;
+/// The directions that the platform is tilted in during one spin cycle.
+pub const SPIN_CYCLE: [RookDirection; 4] = [
+    RookDirection::MINUS_Y,
+    RookDirection::MINUS_X,
+    RookDirection::PLUS_Y,
+    RookDirection::PLUS_X,
+];
 
-        let mut falling_point = match direction.sign() {
-            LineDirection::Negative => 0,
-            LineDirection::Positive => fall_axis_dimension - 1,
-        };
-
-        let range: Box<dyn Iterator<Item = isize>> = match direction.sign() {
-            LineDirection::Negative => Box::new(0..fall_axis_dimension),
-            LineDirection::Positive => Box::new((0..fall_axis_dimension).rev()),
-        };
-
-        for j in range {
-            let mut pos = v!(0, 0);
-            pos[direction.axis()] = j;
-            pos[direction.perpendicular_axis()] = i as isize;
-
-            if round_rocks.remove(&pos) {
-                pos[direction.axis()] = falling_point as isize;
-                round_rocks.insert(pos);
-                falling_point -= direction.sign().to_num::<isize>();
-            } else if grid[pos] == '#' {
-                falling_point = j - direction.sign().to_num::<isize>();
-            }
-        }
+/// `len` set bits, starting from bit `start`.
+fn bits(start: usize, len: usize) -> u128 {
+    if len == 0 {
+        0
+    } else {
+        (u128::MAX >> (u128::BITS as usize - len)) << start
     }
 }
 
-fn spin_cycle(grid: &Grid<char>, round_rocks: &mut HashSet<GridPos>) {
-    tilt_platform(grid, round_rocks, RookDirection::MINUS_Y);
-    tilt_platform(grid, round_rocks, RookDirection::MINUS_X);
-    tilt_platform(grid, round_rocks, RookDirection::PLUS_Y);
-    tilt_platform(grid, round_rocks, RookDirection::PLUS_X);
+/// Splits `0..len` into the maximal runs which don't contain a cube-shaped rock, as pairs of
+/// start and length.
+fn segments(len: usize, is_cube: impl Fn(usize) -> bool) -> Vec<(usize, usize)> {
+    (0..len)
+        .group_by(|&i| is_cube(i))
+        .into_iter()
+        .filter(|(cube, _)| !cube)
+        .map(|(_, mut run)| {
+            let start = run.next().unwrap();
+            (start, 1 + run.count())
+        })
+        .collect()
 }
 
-fn north_total_load(grid: &Grid<char>, round_rocks: &HashSet<GridPos>) -> isize {
-    round_rocks
-        .iter()
-        .map(|round_rock| grid.height() as isize - round_rock[1])
-        .sum()
-}
+/// The parts of the platform which never move: its size, and where the cube-shaped rocks are.
+#[derive(Clone, Debug)]
+pub struct Platform {
+    width: usize,
+    height: usize,
 
-fn log_state(grid: &Grid<char>, round_rocks: &HashSet<GridPos>) {
-    trace!(
-        "\n{}",
-        grid.render(|pos, &c| if c == '#' {
-            '#'
-        } else if round_rocks.contains(&pos) {
-            'O'
-        } else {
-            '.'
-        })
-    );
+    /// Bit `x` of row `y` is set if there is a cube-shaped rock at `(x, y)`.
+    cubes: Vec<u128>,
+
+    /// For each row, the runs of columns that rounded rocks can roll along.
+    row_segments: Vec<Vec<(usize, usize)>>,
+
+    /// For each column, the runs of rows that rounded rocks can roll along.
+    col_segments: Vec<Vec<(usize, usize)>>,
 }
 
-pub fn solve<Part: AocPart>(input: &str) -> isize {
-    let grid: Grid<char> = input.lines().map(str::chars).collect();
+/// Where the rounded rocks are: bit `x` of row `y` is set if there is one at `(x, y)`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Rocks(Vec<u128>);
+
+impl Platform {
+    /// The widest platform that can be packed into bits.
+    pub const MAX_WIDTH: usize = u128::BITS as usize;
 
-    let mut round_rocks: HashSet<GridPos> = grid
-        .positions_zm()
-        .filter(|&pos| grid[pos] == 'O')
-        .collect();
+    pub fn parse(input: &str) -> anyhow::Result<(Self, Rocks)> {
+        let grid: Grid<char> = input.lines().map(str::chars).collect();
 
-    log_state(&grid, &round_rocks);
+        let (width, height) = (grid.width(), grid.height());
+        if width > Self::MAX_WIDTH {
+            bail!(
+                "platform is {width} wide, but at most {} is supported",
+                Self::MAX_WIDTH
+            );
+        }
 
-    if Part::is_one() {
-        tilt_platform(&grid, &mut round_rocks, RookDirection::MINUS_Y);
+        let mut cubes = vec![0; height];
+        let mut rounds = vec![0; height];
 
-        log_state(&grid, &round_rocks);
+        for (pos, &c) in grid.iter_zm_with_pos() {
+            let (x, y) = (pos[0] as usize, pos[1] as usize);
 
-        return north_total_load(&grid, &round_rocks);
+            match c {
+                '#' => cubes[y] |= 1 << x,
+                'O' => rounds[y] |= 1 << x,
+                '.' => (),
+                other => bail!("unknown tile {other:?} at {pos}"),
+            }
+        }
+
+        let row_segments = cubes
+            .iter()
+            .map(|row| segments(width, |x| row >> x & 1 == 1))
+            .collect();
+
+        let col_segments = (0..width)
+            .map(|x| segments(height, |y| cubes[y] >> x & 1 == 1))
+            .collect();
+
+        let platform = Self {
+            width,
+            height,
+            cubes,
+            row_segments,
+            col_segments,
+        };
+
+        Ok((platform, Rocks(rounds)))
     }
 
-    let mut cache: HashMap<u64, usize> = HashMap::new();
+    /// Rolls every rounded rock as far as it will go in `direction`. Each run of tiles between
+    /// cube-shaped rocks only needs to know how many rounded rocks are in it, not where.
+    pub fn tilt(&self, rocks: &mut Rocks, direction: RookDirection) {
+        let Rocks(rows) = rocks;
+        let towards_start = direction.sign() == LineDirection::Negative;
+
+        if direction.axis() == 0 {
+            for (row, segments) in iter::zip(rows, &self.row_segments) {
+                for &(start, len) in segments {
+                    let mask = bits(start, len);
+                    let count = (*row & mask).count_ones() as usize;
 
-    let billion = 1_000_000_000;
-    for i in 0..billion {
-        spin_cycle(&grid, &mut round_rocks);
+                    let offset = if towards_start { 0 } else { len - count };
+                    *row = (*row & !mask) | bits(start + offset, count);
+                }
+            }
+        } else {
+            for (x, segments) in self.col_segments.iter().enumerate() {
+                let bit = 1 << x;
 
-        trace!(i);
-        log_state(&grid, &round_rocks);
+                for &(start, len) in segments {
+                    let segment = &mut rows[start..start + len];
 
-        // std::thread::sleep(std::time::Duration::from_secs(1));
+                    let mut count = 0;
+                    for row in segment.iter_mut() {
+                        count += (*row & bit != 0) as usize;
+                        *row &= !bit;
+                    }
 
-        let hash = round_rocks.iter().hash();
-        if let Some(prev_i) = cache.insert(hash, i) {
-            trace!(prev_i, i);
-            let remaining_i = billion - i;
-            let cycle_step = i - prev_i;
-            let remaining_cycles = remaining_i / cycle_step;
-            let jump_i = 1 + i + cycle_step * remaining_cycles;
-            for _ in jump_i..billion {
-                spin_cycle(&grid, &mut round_rocks);
+                    let offset = if towards_start { 0 } else { len - count };
+                    for row in &mut segment[offset..offset + count] {
+                        *row |= bit;
+                    }
+                }
             }
-            break;
         }
     }
 
-    north_total_load(&grid, &round_rocks)
+    /// Tilts the platform in each of `directions` in turn.
+    pub fn tilt_all(&self, rocks: &mut Rocks, directions: &[RookDirection]) {
+        for &direction in directions {
+            self.tilt(rocks, direction);
+        }
+    }
+
+    pub fn spin_cycle(&self, rocks: &mut Rocks) {
+        self.tilt_all(rocks, &SPIN_CYCLE);
+    }
+
+    /// Where the rounded rocks end up after tilting through `directions` `times` times over.
+    /// Since the rocks soon fall into a cycle, this is quick even for huge `times`.
+    pub fn repeat(&self, rocks: Rocks, directions: &[RookDirection], times: usize) -> Rocks {
+        cycles::fast_forward(rocks, times, |rocks| {
+            self.tilt_all(rocks, directions);
+            trace!("\n{}", self.render(rocks));
+        })
+    }
+
+    /// The total load on the support beams along the edge of the platform in direction `edge`.
+    /// Each rounded rock adds the number of rows or columns from it to the opposite edge,
+    /// counting its own.
+    pub fn load(&self, rocks: &Rocks, edge: RookDirection) -> usize {
+        let size = [self.width, self.height][edge.axis()];
+
+        rocks
+            .0
+            .iter()
+            .enumerate()
+            .flat_map(|(y, &row)| {
+                (0..self.width)
+                    .filter(move |x| row >> x & 1 == 1)
+                    .map(move |x| [x, y])
+            })
+            .map(|pos| match edge.sign() {
+                LineDirection::Negative => size - pos[edge.axis()],
+                LineDirection::Positive => pos[edge.axis()] + 1,
+            })
+            .sum()
+    }
+
+    pub fn render(&self, rocks: &Rocks) -> String {
+        (0..self.height)
+            .map(|y| {
+                (0..self.width)
+                    .map(|x| {
+                        if self.cubes[y] >> x & 1 == 1 {
+                            '#'
+                        } else if rocks.0[y] >> x & 1 == 1 {
+                            'O'
+                        } else {
+                            '.'
+                        }
+                    })
+                    .collect::<String>()
+            })
+            .join("\n")
+    }
 }
 
-aoc_tests! {
-    inputs {
-        ex = "O....#....
+/// Runs a [`TiltProgram`] on the platform, then measures the load on the beams along `edge`.
+pub fn run_program(input: &str, program: &str, edge: RookDirection) -> anyhow::Result<usize> {
+    let (platform, rocks) = Platform::parse(input)?;
+    let program: TiltProgram = program.parse()?;
+
+    trace!("\n{}", platform.render(&rocks));
+
+    let rocks = program.run(&platform, rocks);
+
+    Ok(platform.load(&rocks, edge))
+}
+
+pub fn solve<Part: AocPart>(input: &str) -> usize {
+    let program = if Part::is_one() {
+        "N"
+    } else {
+        "NWSE*1000000000"
+    };
+
+    run_program(input, program, RookDirection::MINUS_Y).expect("platform should parse")
+}
+
+#[cfg(test)]
+const EXAMPLE_INPUT: &str = "O....#....
 O.OO#....#
 .....##...
 OO.#O....O
@@ -118,7 +239,11 @@ O.#..O.#.#
 ..O..#O..O
 .......O..
 #....###..
-#OO..#....",
+#OO..#....";
+
+aoc_tests! {
+    inputs {
+        ex = EXAMPLE_INPUT,
     }
 
     part::One {
@@ -131,3 +256,65 @@ O.#..O.#.#
         rb: @input => 91286,
     }
 }
+
+#[test]
+fn tilt_each_way() {
+    let (platform, rocks) = Platform::parse("O.#O.\n.O..O\n#O.O.").unwrap();
+
+    let tilted = |direction| {
+        let mut rocks = rocks.clone();
+        platform.tilt(&mut rocks, direction);
+        platform.render(&rocks)
+    };
+
+    assert_eq!(tilted(RookDirection::MINUS_X), "O.#O.\nOO...\n#OO..");
+    assert_eq!(tilted(RookDirection::PLUS_X), ".O#.O\n...OO\n#..OO");
+    assert_eq!(tilted(RookDirection::MINUS_Y), "OO#OO\n.O.O.\n#....");
+    assert_eq!(tilted(RookDirection::PLUS_Y), "..#..\nOO.O.\n#O.OO");
+}
+
+#[test]
+fn repeated_tilts() {
+    let (platform, rocks) = Platform::parse(EXAMPLE_INPUT).unwrap();
+
+    // the example settles into a cycle of length 7 after 3 spin cycles
+    let after = |times| platform.repeat(rocks.clone(), &SPIN_CYCLE, times);
+    assert_eq!(after(1_000_000_000), after(3 + (1_000_000_000 - 3) % 7));
+    assert_eq!(
+        platform.load(&after(1_000_000_000), RookDirection::MINUS_Y),
+        64
+    );
+
+    // tilting back and forth settles immediately
+    let east_west = [RookDirection::PLUS_X, RookDirection::MINUS_X];
+    let once = platform.repeat(rocks.clone(), &east_west, 1);
+    assert_eq!(platform.repeat(rocks, &east_west, 12345), once);
+}
+
+#[test]
+fn loads_on_each_edge() {
+    let (platform, rocks) = Platform::parse("O.#O.\n.O..O\n#O.O.").unwrap();
+
+    assert_eq!(
+        platform.load(&rocks, RookDirection::MINUS_Y),
+        3 + 3 + 2 + 2 + 1 + 1
+    );
+    assert_eq!(
+        platform.load(&rocks, RookDirection::PLUS_Y),
+        1 + 1 + 2 + 2 + 3 + 3
+    );
+    assert_eq!(
+        platform.load(&rocks, RookDirection::MINUS_X),
+        5 + 2 + 4 + 1 + 4 + 2
+    );
+    assert_eq!(
+        platform.load(&rocks, RookDirection::PLUS_X),
+        1 + 4 + 2 + 5 + 2 + 4
+    );
+
+    // after tilting east, the rocks are all against the east edge
+    assert_eq!(
+        run_program(EXAMPLE_INPUT, "NWSE*1000000000, E", RookDirection::PLUS_X).unwrap(),
+        run_program(EXAMPLE_INPUT, "NWSE*1000000000, E*3", RookDirection::PLUS_X).unwrap()
+    );
+}
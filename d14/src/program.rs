@@ -0,0 +1,98 @@
+use super::*;
+
+/// One step of a [`TiltProgram`]: tilting in each of `directions` in turn, `times` times over.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TiltCommand {
+    pub directions: Vec<RookDirection>,
+    pub times: usize,
+}
+
+/// A sequence of tilts, written as comma-separated commands. Each command is a string of the
+/// directions `N`, `W`, `S` and `E`, optionally followed by `*` and how many times to repeat it,
+/// so the puzzle's part two is `NWSE*1000000000`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TiltProgram(pub Vec<TiltCommand>);
+
+fn parse_direction(c: char) -> anyhow::Result<RookDirection> {
+    Ok(match c {
+        'N' => RookDirection::MINUS_Y,
+        'W' => RookDirection::MINUS_X,
+        'S' => RookDirection::PLUS_Y,
+        'E' => RookDirection::PLUS_X,
+        other => bail!("unknown direction {other:?}"),
+    })
+}
+
+impl FromStr for TiltCommand {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (directions, times) = match s.split_once('*') {
+            Some((directions, times)) => (
+                directions,
+                times
+                    .trim()
+                    .parse()
+                    .with_context(|| format!("bad repeat count in '{s}'"))?,
+            ),
+            None => (s, 1),
+        };
+
+        let directions = directions
+            .trim()
+            .chars()
+            .map(parse_direction)
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        if directions.is_empty() {
+            bail!("command '{s}' has no directions");
+        }
+
+        Ok(Self { directions, times })
+    }
+}
+
+impl FromStr for TiltProgram {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.split(',')
+            .map(str::parse)
+            .collect::<anyhow::Result<_>>()
+            .map(Self)
+    }
+}
+
+impl TiltProgram {
+    /// Where the rounded rocks end up after running every command in order.
+    pub fn run(&self, platform: &Platform, mut rocks: Rocks) -> Rocks {
+        for command in &self.0 {
+            debug!("running {command:?}");
+            rocks = platform.repeat(rocks, &command.directions, command.times);
+        }
+
+        rocks
+    }
+}
+
+#[test]
+fn parse_programs() {
+    let program: TiltProgram = "NWSE*1000000000, N".parse().unwrap();
+    assert_eq!(
+        program,
+        TiltProgram(vec![
+            TiltCommand {
+                directions: SPIN_CYCLE.to_vec(),
+                times: 1_000_000_000,
+            },
+            TiltCommand {
+                directions: vec![RookDirection::MINUS_Y],
+                times: 1,
+            },
+        ])
+    );
+
+    assert!("NX".parse::<TiltProgram>().is_err());
+    assert!("N*".parse::<TiltProgram>().is_err());
+    assert!("N,,S".parse::<TiltProgram>().is_err());
+}
@@ -0,0 +1,15 @@
+//! Replays an initialization sequence read from stdin, printing the boxes after every step.
+//!
+//! Pass `--changed` to only print the box that each step changed.
+
+use d15::{replay_stream, ReplayDetail};
+
+fn main() -> anyhow::Result<()> {
+    let detail = if std::env::args().any(|arg| arg == "--changed") {
+        ReplayDetail::ChangedBox
+    } else {
+        ReplayDetail::AllBoxes
+    };
+
+    replay_stream(std::io::stdin().lock(), std::io::stdout().lock(), detail)
+}
@@ -1,13 +1,252 @@
+mod replay;
+
 use aocutil::prelude::*;
 
+pub use replay::{replay, replay_stream, ReplayDetail};
+
 pub const YEAR: usize = 2023;
 
 pub const DAY: usize = 15;
 
-fn holiday_hash(input: &str) -> usize {
+pub fn holiday_hash(input: &str) -> usize {
     input.chars().fold(0, |h, c| ((h + c as usize) * 17) % 256)
 }
 
+/// One step of the initialization sequence.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Step {
+    /// `label=focal_length`
+    Insert(String, usize),
+
+    /// `label-`
+    Remove(String),
+}
+
+impl FromStr for Step {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (label, focal_length) = s
+            .split_once(['-', '='])
+            .with_context(|| format!("step '{s}' should have an '=' or '-'"))?;
+
+        Ok(if focal_length.is_empty() {
+            Self::Remove(label.to_string())
+        } else {
+            let focal_length = focal_length
+                .parse()
+                .with_context(|| format!("bad focal length in '{s}'"))?;
+            Self::Insert(label.to_string(), focal_length)
+        })
+    }
+}
+
+/// How to put the boxes back the way they were before a step.
+#[derive(Clone, Debug)]
+enum Undo {
+    /// The lens in `slot` was new, so take it out again.
+    Insert { bucket: usize, slot: usize },
+
+    /// The lens in `slot` had its focal length changed from `focal_length`.
+    Replace {
+        bucket: usize,
+        slot: usize,
+        focal_length: usize,
+    },
+
+    /// A lens was taken out of `slot`, so put it back.
+    Remove {
+        bucket: usize,
+        slot: usize,
+        label: String,
+        focal_length: usize,
+    },
+}
+
+/// The Holiday ASCII String Helper Manual Arrangement Procedure: a row of boxes of labelled lenses,
+/// which labels are hashed into.
+///
+/// The hash function and the number of boxes can be changed, to see how it behaves with more or
+/// fewer collisions; the puzzle uses [`holiday_hash`] and 256 boxes.
+#[derive(Clone, Debug)]
+pub struct LensBoxes<H = fn(&str) -> usize> {
+    hash: H,
+
+    /// For each box, its lenses from front to back, as pairs of label and focal length.
+    boxes: Vec<Vec<(String, usize)>>,
+
+    /// For each step which changed anything, how to undo it.
+    undo_log: Vec<Undo>,
+
+    /// Whether to add to `undo_log` at all.
+    record_undo: bool,
+}
+
+impl Default for LensBoxes {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LensBoxes {
+    pub fn new() -> Self {
+        Self::with_hasher(holiday_hash, 256)
+    }
+}
+
+impl<H: Fn(&str) -> usize> LensBoxes<H> {
+    /// Panics if there are no boxes.
+    pub fn with_hasher(hash: H, num_boxes: usize) -> Self {
+        assert!(num_boxes > 0, "there should be at least one box");
+
+        Self {
+            hash,
+            boxes: vec![Vec::new(); num_boxes],
+            undo_log: Vec::new(),
+            record_undo: true,
+        }
+    }
+
+    /// Stops keeping track of how to undo steps, and forgets any already taken, for when only the
+    /// final arrangement matters. [`undo`](Self::undo) then has nothing to revert.
+    pub fn without_undo_log(mut self) -> Self {
+        self.undo_log = Vec::new();
+        self.record_undo = false;
+        self
+    }
+
+    /// The box that lenses labelled `label` go in.
+    pub fn box_of(&self, label: &str) -> usize {
+        (self.hash)(label) % self.boxes.len()
+    }
+
+    /// The lenses in box `idx`, from front to back, as pairs of label and focal length.
+    pub fn lenses(&self, idx: usize) -> &[(String, usize)] {
+        &self.boxes[idx]
+    }
+
+    /// Every box with at least one lens in it, along with its index.
+    pub fn occupied(&self) -> impl Iterator<Item = (usize, &[(String, usize)])> {
+        self.boxes
+            .iter()
+            .enumerate()
+            .filter(|(_, lenses)| !lenses.is_empty())
+            .map(|(idx, lenses)| (idx, lenses.as_slice()))
+    }
+
+    /// Puts a lens in the right box, replacing any with the same label in place or else going
+    /// behind the rest. Returns the focal length of the lens it replaced, if any.
+    pub fn insert(&mut self, label: &str, focal_length: usize) -> Option<usize> {
+        let bucket = self.box_of(label);
+        let lenses = &mut self.boxes[bucket];
+
+        match lenses.iter().position(|(l, _)| l == label) {
+            Some(slot) => {
+                let old = std::mem::replace(&mut lenses[slot].1, focal_length);
+                if self.record_undo {
+                    self.undo_log.push(Undo::Replace {
+                        bucket,
+                        slot,
+                        focal_length: old,
+                    });
+                }
+                Some(old)
+            }
+            None => {
+                lenses.push((label.to_string(), focal_length));
+                if self.record_undo {
+                    self.undo_log.push(Undo::Insert {
+                        bucket,
+                        slot: lenses.len() - 1,
+                    });
+                }
+                None
+            }
+        }
+    }
+
+    /// Takes the lens labelled `label` out of its box, if there is one, and returns its focal
+    /// length. The lenses behind it move forward.
+    pub fn remove(&mut self, label: &str) -> Option<usize> {
+        let bucket = self.box_of(label);
+        let lenses = &mut self.boxes[bucket];
+
+        let slot = lenses.iter().position(|(l, _)| l == label)?;
+        let (label, focal_length) = lenses.remove(slot);
+
+        if self.record_undo {
+            self.undo_log.push(Undo::Remove {
+                bucket,
+                slot,
+                label,
+                focal_length,
+            });
+        }
+
+        Some(focal_length)
+    }
+
+    /// Carries out `step`, returning the box it was about.
+    pub fn apply(&mut self, step: &Step) -> usize {
+        match step {
+            Step::Insert(label, focal_length) => {
+                self.insert(label, *focal_length);
+                self.box_of(label)
+            }
+            Step::Remove(label) => {
+                self.remove(label);
+                self.box_of(label)
+            }
+        }
+    }
+
+    /// Reverts the most recent insert or remove which changed anything. Returns `false` if there
+    /// was nothing left to undo.
+    pub fn undo(&mut self) -> bool {
+        let Some(undo) = self.undo_log.pop() else {
+            return false;
+        };
+
+        match undo {
+            Undo::Insert { bucket, slot } => {
+                self.boxes[bucket].remove(slot);
+            }
+            Undo::Replace {
+                bucket,
+                slot,
+                focal_length,
+            } => {
+                self.boxes[bucket][slot].1 = focal_length;
+            }
+            Undo::Remove {
+                bucket,
+                slot,
+                label,
+                focal_length,
+            } => {
+                self.boxes[bucket].insert(slot, (label, focal_length));
+            }
+        }
+
+        true
+    }
+
+    /// The sum over every lens of one plus its box's index, times its slot counting from one,
+    /// times its focal length.
+    pub fn focusing_power(&self) -> usize {
+        self.boxes
+            .iter()
+            .enumerate()
+            .flat_map(|(i, lenses)| {
+                lenses
+                    .iter()
+                    .enumerate()
+                    .map(move |(j, (_, f))| (1 + i) * (1 + j) * f)
+            })
+            .sum()
+    }
+}
+
 pub fn solve<Part: AocPart>(input: &str) -> usize {
     let steps = input.split(',');
 
@@ -15,40 +254,22 @@ pub fn solve<Part: AocPart>(input: &str) -> usize {
         return steps.map(holiday_hash).sum();
     }
 
-    let mut boxes: Vec<Vec<(&str, usize)>> = vec![Vec::new(); 256];
+    let mut boxes = LensBoxes::new().without_undo_log();
 
     for step in steps {
-        let (label, focal_length) = step.split_once(['-', '=']).unwrap();
-        let hash = holiday_hash(label);
-
-        if focal_length.is_empty() {
-            boxes[hash].retain(|(l, _)| l != &label);
-        } else {
-            let focal_length = focal_length.parse::<usize>().unwrap();
-            if let Some((_, f)) = boxes[hash].iter_mut().find(|(l, _)| l == &label) {
-                *f = focal_length;
-            } else {
-                boxes[hash].push((label, focal_length));
-            }
-        }
+        let step: Step = step.parse().expect("step should parse");
+        boxes.apply(&step);
     }
 
-    boxes
-        .iter()
-        .enumerate()
-        .map(|(i, lenses)| {
-            lenses
-                .iter()
-                .enumerate()
-                .map(move |(j, (_, f))| (1 + i) * (1 + j) * f)
-        })
-        .flatten()
-        .sum()
+    boxes.focusing_power()
 }
 
+#[cfg(test)]
+const EXAMPLE_INPUT: &str = "rn=1,cm-,qp=3,cm=2,qp-,pc=4,ot=9,ab=5,pc-,pc=6,ot=7";
+
 aoc_tests! {
     inputs {
-        e0 = "rn=1,cm-,qp=3,cm=2,qp-,pc=4,ot=9,ab=5,pc-,pc=6,ot=7",
+        e0 = EXAMPLE_INPUT,
     }
 
     part::One {
@@ -61,3 +282,55 @@ aoc_tests! {
         rb: @input => 264021,
     }
 }
+
+#[test]
+fn lens_boxes() {
+    let steps: Vec<Step> = EXAMPLE_INPUT
+        .split(',')
+        .map(|s| s.parse().unwrap())
+        .collect();
+
+    let mut boxes = LensBoxes::new();
+    for step in &steps {
+        boxes.apply(step);
+    }
+
+    assert_eq!(boxes.occupied().map(|(idx, _)| idx).collect_vec(), [0, 3]);
+    assert_eq!(
+        boxes.lenses(3),
+        [
+            ("ot".to_string(), 7),
+            ("ab".to_string(), 5),
+            ("pc".to_string(), 6)
+        ]
+    );
+
+    // `cm-` at the start removed nothing, so there are only ten steps to undo
+    for _ in 0..10 {
+        assert!(boxes.undo());
+    }
+    assert!(!boxes.undo());
+    assert_eq!(boxes.occupied().count(), 0);
+
+    let mut unlogged = LensBoxes::new().without_undo_log();
+    for step in &steps {
+        unlogged.apply(step);
+    }
+    assert_eq!(unlogged.focusing_power(), 145);
+    assert!(!unlogged.undo());
+
+    // with a single box, every lens collides
+    let mut one_box = LensBoxes::with_hasher(|_: &str| 0, 1);
+    for step in &steps {
+        one_box.apply(step);
+    }
+    assert_eq!(
+        one_box
+            .lenses(0)
+            .iter()
+            .map(|(l, f)| (l.as_str(), *f))
+            .collect_vec(),
+        [("rn", 1), ("cm", 2), ("ot", 7), ("ab", 5), ("pc", 6)]
+    );
+    assert_eq!(one_box.focusing_power(), 1 + 2 * 2 + 3 * 7 + 4 * 5 + 5 * 6);
+}
@@ -0,0 +1,132 @@
+use super::*;
+
+use std::io::{BufRead, Write};
+
+/// How much of the boxes to show after each step of a replay.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum ReplayDetail {
+    /// Every box with a lens in it, exactly like the puzzle's walkthrough.
+    #[default]
+    AllBoxes,
+
+    /// Only the box that the step was about, and only if the step changed it.
+    ChangedBox,
+}
+
+impl<H: Fn(&str) -> usize> LensBoxes<H> {
+    /// One box as the puzzle shows it, e.g. `Box 3: [ot 7] [ab 5] [pc 6]`.
+    pub fn render_box(&self, idx: usize) -> String {
+        let lenses = self.boxes[idx]
+            .iter()
+            .map(|(label, focal_length)| format!("[{label} {focal_length}]"))
+            .join(" ");
+
+        format!("Box {idx}: {lenses}")
+    }
+
+    /// Carries out the step written as `text`, then describes it and the boxes afterwards, e.g.
+    ///
+    /// ```text
+    /// After "rn=1":
+    /// Box 0: [rn 1]
+    /// ```
+    pub fn replay_step(&mut self, text: &str, detail: ReplayDetail) -> anyhow::Result<String> {
+        let step: Step = text.parse()?;
+
+        // the undo log may be off, so see what changed from the step itself
+        let (touched, changed) = match &step {
+            Step::Insert(label, focal_length) => {
+                self.insert(label, *focal_length);
+                (self.box_of(label), true)
+            }
+            Step::Remove(label) => (self.box_of(label), self.remove(label).is_some()),
+        };
+
+        let mut report = format!("After \"{text}\":\n");
+
+        match detail {
+            ReplayDetail::AllBoxes => {
+                for (idx, _) in self.occupied() {
+                    writeln!(report, "{}", self.render_box(idx)).unwrap();
+                }
+            }
+            ReplayDetail::ChangedBox if changed => {
+                writeln!(report, "{}", self.render_box(touched)).unwrap();
+            }
+            ReplayDetail::ChangedBox => (),
+        }
+
+        Ok(report)
+    }
+}
+
+/// Replays the comma-separated initialization sequence `input` with the puzzle's boxes,
+/// describing each step and the boxes after it, with a blank line between steps.
+pub fn replay(input: &str, detail: ReplayDetail) -> anyhow::Result<String> {
+    let mut boxes = LensBoxes::new().without_undo_log();
+
+    let reports = input
+        .trim()
+        .split(',')
+        .map(|text| boxes.replay_step(text, detail))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    Ok(reports.join("\n"))
+}
+
+/// Like [`replay`], but reads steps from `reader` a line at a time and writes the reports for a
+/// line's steps to `writer` as soon as the line has been read. Steps may be separated by commas or
+/// whitespace, so they can be typed one per line.
+pub fn replay_stream(
+    reader: impl BufRead,
+    mut writer: impl Write,
+    detail: ReplayDetail,
+) -> anyhow::Result<()> {
+    let mut boxes = LensBoxes::new().without_undo_log();
+
+    for line in reader.lines() {
+        let line = line.context("steps should be UTF-8 text")?;
+
+        for text in line
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|text| !text.is_empty())
+        {
+            writeln!(writer, "{}", boxes.replay_step(text, detail)?)?;
+            writer.flush()?;
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn replay_example() {
+    let transcript = replay(EXAMPLE_INPUT, ReplayDetail::AllBoxes).unwrap();
+
+    assert!(transcript.starts_with(indoc::indoc! {r#"
+        After "rn=1":
+        Box 0: [rn 1]
+
+        After "cm-":
+        Box 0: [rn 1]
+
+        After "qp=3":
+        Box 0: [rn 1]
+        Box 1: [qp 3]
+    "#}));
+    assert!(transcript.ends_with(indoc::indoc! {r#"
+        After "ot=7":
+        Box 0: [rn 1] [cm 2]
+        Box 3: [ot 7] [ab 5] [pc 6]
+    "#}));
+
+    let mut streamed = Vec::new();
+    let input = EXAMPLE_INPUT.replace(',', "\n");
+    replay_stream(input.as_bytes(), &mut streamed, ReplayDetail::ChangedBox).unwrap();
+
+    let streamed = String::from_utf8(streamed).unwrap();
+    assert!(streamed.starts_with("After \"rn=1\":\nBox 0: [rn 1]\n\nAfter \"cm-\":\n\nAfter"));
+    assert!(streamed.ends_with("After \"ot=7\":\nBox 3: [ot 7] [ab 5] [pc 6]\n\n"));
+
+    assert!(replay("rn=1,cm", ReplayDetail::AllBoxes).is_err());
+}
@@ -4,48 +4,158 @@ pub const YEAR: usize = 2023;
 
 pub const DAY: usize = 4;
 
-fn score(card: &str) -> (usize, usize) {
-    let (x, y) = card.split_once("|").unwrap();
-
-    let winning: HashSet<u32> = ints(x).skip(1).collect();
-    let mut score = 0;
-    let mut matching = 0;
-
-    for have in ints::<u32>(y) {
-        if winning.contains(&have) {
-            matching += 1;
-            if score == 0 {
-                score = 1;
-            } else {
-                score *= 2;
-            }
+#[derive(Clone, Debug)]
+pub struct Scratchcard {
+    pub id: usize,
+    pub winning: HashSet<u32>,
+    pub held: Vec<u32>,
+}
+
+impl FromStr for Scratchcard {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (header, numbers) = s.split_once(':').context("card should have a ':'")?;
+        let (winning, held) = numbers.split_once('|').context("card should have a '|'")?;
+
+        Ok(Self {
+            id: ints(header).next().context("card should have an id")?,
+            winning: ints(winning).collect(),
+            held: ints(held).collect(),
+        })
+    }
+}
+
+impl Scratchcard {
+    /// The number of held numbers which are also winning numbers.
+    pub fn matching(&self) -> usize {
+        self.held
+            .iter()
+            .filter(|have| self.winning.contains(have))
+            .count()
+    }
+
+    /// One point for the first match, doubled for every match after that.
+    pub fn score(&self) -> usize {
+        match self.matching() {
+            0 => 0,
+            matching => 1 << (matching - 1),
         }
     }
+}
+
+/// Decides which cards a winning card awards copies of.
+pub trait PrizeRule {
+    /// The indices (not ids) of the cards won by one copy of the card at index `idx`, which has
+    /// `matching` matching numbers, out of `num_cards` cards in total.
+    fn prizes(&self, idx: usize, matching: usize, num_cards: usize) -> impl Iterator<Item = usize>;
 
-    (score, matching)
+    /// The most copies of any one card that may be held, including the original.
+    fn max_copies(&self) -> usize {
+        usize::MAX
+    }
 }
 
-pub fn solve<Part: AocPart>(input: &str) -> usize {
-    let mut cards: Vec<(&str, usize)> = input.lines().map(|line| (line, 1)).collect();
+/// The puzzle's rule: a card with `n` matches wins one copy of each of the next `n` cards.
+pub struct NextCards;
 
-    if Part::is_one() {
-        return cards.iter().map(|(card, _)| score(card).0).sum();
+impl PrizeRule for NextCards {
+    fn prizes(&self, idx: usize, matching: usize, num_cards: usize) -> impl Iterator<Item = usize> {
+        (idx + 1)..cmp::min(idx + 1 + matching, num_cards)
     }
+}
 
-    for idx in 0..cards.len() {
-        let (card, copies) = cards[idx];
-        trace!("looking at Card {}", idx + 1);
+/// A card with `n` matches wins one copy of each of the previous `n` cards. Prizes only ever go to
+/// earlier cards, so the first card wins nothing and the cascade always ends.
+pub struct PreviousCards;
+
+impl PrizeRule for PreviousCards {
+    fn prizes(
+        &self,
+        idx: usize,
+        matching: usize,
+        _num_cards: usize,
+    ) -> impl Iterator<Item = usize> {
+        idx.saturating_sub(matching)..idx
+    }
+}
 
-        let (score, matching) = score(&card);
+/// Follows `rule`, but never holds more than `max_copies` of any one card; extra prizes are
+/// forfeited.
+pub struct Capped<R> {
+    pub rule: R,
+    pub max_copies: usize,
+}
+
+impl<R: PrizeRule> PrizeRule for Capped<R> {
+    fn prizes(&self, idx: usize, matching: usize, num_cards: usize) -> impl Iterator<Item = usize> {
+        self.rule.prizes(idx, matching, num_cards)
+    }
 
-        trace!("  score = {score}, and there are {copies} copies");
+    fn max_copies(&self) -> usize {
+        cmp::min(self.max_copies, self.rule.max_copies())
+    }
+}
 
-        for i in (idx + 1)..=(idx + matching) {
-            cards[i].1 += copies;
+/// The result of cashing in a pile of scratchcards until no more prizes are left.
+#[derive(Clone, Debug)]
+pub struct Cascade {
+    /// For each card in input order, how many copies of it are held, including the original.
+    pub copies: Vec<usize>,
+
+    /// For each card in input order, the ids of the cards which awarded it copies, and how many
+    /// copies each of them awarded.
+    pub provenance: Vec<BTreeMap<usize, usize>>,
+}
+
+impl Cascade {
+    /// Cash in every copy of every card in `cards` under `rule`. Copies are cashed in lowest index
+    /// first, so under [`NextCards`] every card is only looked at once.
+    pub fn run(cards: &[Scratchcard], rule: &impl PrizeRule) -> Self {
+        let matching: Vec<usize> = cards.iter().map(Scratchcard::matching).collect();
+
+        let mut copies = vec![1; cards.len()];
+        let mut provenance = vec![BTreeMap::new(); cards.len()];
+
+        // copies which have been won but not yet cashed in, keyed by card index
+        let mut pending: BTreeMap<usize, usize> = (0..cards.len()).map(|idx| (idx, 1)).collect();
+
+        while let Some((idx, count)) = pending.pop_first() {
+            trace!("cashing in {count} copies of Card {}", cards[idx].id);
+
+            for prize in rule.prizes(idx, matching[idx], cards.len()) {
+                let won = cmp::min(count, rule.max_copies().saturating_sub(copies[prize]));
+                if won == 0 {
+                    continue;
+                }
+
+                trace!("  won {won} copies of Card {}", cards[prize].id);
+
+                copies[prize] += won;
+                *provenance[prize].entry(cards[idx].id).or_insert(0) += won;
+                *pending.entry(prize).or_insert(0) += won;
+            }
         }
+
+        Self { copies, provenance }
     }
 
-    cards.iter().map(|(_card, count)| count).sum()
+    pub fn total(&self) -> usize {
+        self.copies.iter().sum()
+    }
+}
+
+pub fn solve<Part: AocPart>(input: &str) -> usize {
+    let cards: Vec<Scratchcard> = input
+        .lines()
+        .map(|line| line.parse().expect("card should parse"))
+        .collect();
+
+    if Part::is_one() {
+        return cards.iter().map(Scratchcard::score).sum();
+    }
+
+    Cascade::run(&cards, &NextCards).total()
 }
 
 const EXAMPLE_INPUT: &str = "Card 1: 41 48 83 86 17 | 83 86  6 31 17  9 48 53
@@ -62,6 +172,31 @@ example_tests! {
         b0: EXAMPLE_INPUT => 30,
 }
 
+#[test]
+fn example_provenance() {
+    let cards: Vec<Scratchcard> = EXAMPLE_INPUT.lines().map(|l| l.parse().unwrap()).collect();
+
+    let cascade = Cascade::run(&cards, &NextCards);
+    assert_eq!(cascade.copies, [1, 2, 4, 8, 14, 1]);
+    assert_eq!(
+        cascade.provenance[3],
+        BTreeMap::from([(1, 1), (2, 2), (3, 4)])
+    );
+
+    let capped = Cascade::run(
+        &cards,
+        &Capped {
+            rule: NextCards,
+            max_copies: 4,
+        },
+    );
+    assert_eq!(capped.copies, [1, 2, 4, 4, 4, 1]);
+
+    let backwards = Cascade::run(&cards, &PreviousCards);
+    assert_eq!(backwards.copies, [6, 3, 2, 1, 1, 1]);
+    assert_eq!(backwards.provenance[0], BTreeMap::from([(2, 3), (3, 2)]));
+}
+
 #[test]
 fn part_one() {
     let _ = aocutil::log::test_subscriber().try_init();
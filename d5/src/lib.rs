@@ -4,76 +4,299 @@ const YEAR: usize = 2023;
 
 const DAY: usize = 5;
 
-fn solve_b(input: &str) -> i64 {
-    let mut pars = input.split("\n\n");
+/// A map on the integers which shifts each of a set of disjoint ranges by a constant, and is the
+/// identity everywhere else.
+#[derive(Clone, Debug, Default)]
+pub struct PiecewiseMap {
+    /// Pairs of source ranges and the deltas to add to them, sorted by start. No two ranges
+    /// overlap, no delta is zero, and adjacent ranges never share a delta.
+    pieces: Vec<(Range<i64>, i64)>,
+}
 
-    let mut seeds: RangeSet<i64> = RangeSet::new();
-    for [a, b] in ints::<i64>(pars.next().unwrap()).util_array_chunks() {
-        seeds.insert(Range::new(a, a + b - 1));
-    }
+impl FromStr for PiecewiseMap {
+    type Err = anyhow::Error;
 
-    for par in pars {
-        debug!("{seeds:?}");
-        let map = par
+    /// Parses the lines of an almanac section after its header, each of which is
+    /// `<destination start> <source start> <length>`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let pieces = s
             .lines()
-            .skip(1)
             .map(|line| {
-                let ns = ints::<i64>(line).vec();
-                (Range::new(ns[1], ns[1] + ns[2] - 1), ns[0] - ns[1])
+                let [dst, src, len] = ints::<i64>(line)
+                    .fill_array()
+                    .context("map lines should have three numbers")?;
+                Ok((Range::new(src, src + len - 1), dst - src))
             })
-            .vec();
-
-        let mut new_seeds: RangeSet<i64> = RangeSet::new();
-        let mut remaining_seeds: RangeSet<i64> = seeds.clone();
-
-        for (source_range, delta) in map {
-            debug!("  mapping {source_range:?}: {delta}");
-            for seed_range in seeds.ranges() {
-                debug!("    looking at seed range {seed_range:?}");
-                let intersection = source_range.intersection(*seed_range);
-                if intersection.start <= intersection.end {
-                    debug!("      found intersection {intersection:?}");
-                    new_seeds.insert(intersection.map(|bound| bound + delta));
-                    remaining_seeds.remove(intersection);
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        Self::new(pieces)
+    }
+}
+
+impl PiecewiseMap {
+    pub fn identity() -> Self {
+        Self::default()
+    }
+
+    /// Fails if any two of the source ranges overlap.
+    pub fn new(pieces: impl IntoIterator<Item = (Range<i64>, i64)>) -> anyhow::Result<Self> {
+        let mut sorted: Vec<(Range<i64>, i64)> = pieces
+            .into_iter()
+            .filter(|(range, delta)| range.start <= range.end && *delta != 0)
+            .collect();
+        sorted.sort_by_key(|(range, _)| range.start);
+
+        let mut pieces: Vec<(Range<i64>, i64)> = Vec::with_capacity(sorted.len());
+
+        for (range, delta) in sorted {
+            match pieces.last_mut() {
+                Some((prev, _)) if prev.end >= range.start => {
+                    bail!("source ranges {prev:?} and {range:?} overlap");
+                }
+                Some((prev, prev_delta)) if prev.end + 1 == range.start && *prev_delta == delta => {
+                    prev.end = range.end;
                 }
+                _ => pieces.push((range, delta)),
             }
         }
 
-        debug!("  remaining seeds: {remaining_seeds:?}");
+        Ok(Self { pieces })
+    }
+
+    pub fn get(&self, x: i64) -> i64 {
+        self.pieces
+            .iter()
+            .find(|(range, _)| range.contains(&x))
+            .map_or(x, |(_, delta)| x + delta)
+    }
+
+    /// Cuts `range` into consecutive pieces, each paired with the delta this map adds to it.
+    fn split(&self, range: Range<i64>) -> Vec<(Range<i64>, i64)> {
+        let mut res = Vec::new();
 
-        for seed_range in remaining_seeds.into_ranges() {
-            new_seeds.insert(seed_range);
+        if range.start > range.end {
+            return res;
         }
 
-        seeds = new_seeds;
+        let mut cursor = range.start;
+
+        for &(piece, delta) in &self.pieces {
+            if piece.end < cursor {
+                continue;
+            }
+
+            if piece.start > range.end {
+                break;
+            }
+
+            if piece.start > cursor {
+                res.push((Range::new(cursor, piece.start - 1), 0));
+            }
+
+            let overlap = Range::new(
+                cmp::max(cursor, piece.start),
+                cmp::min(range.end, piece.end),
+            );
+            res.push((overlap, delta));
+
+            if overlap.end == range.end {
+                return res;
+            }
+
+            cursor = overlap.end + 1;
+        }
+
+        res.push((Range::new(cursor, range.end), 0));
+
+        res
+    }
+
+    /// The map which applies `self`, then `next`.
+    pub fn then(&self, next: &Self) -> Self {
+        let pieces = self
+            .split(Range::new(i64::MIN, i64::MAX))
+            .into_iter()
+            .flat_map(|(range, delta)| {
+                next.split(range.map(|x| x + delta))
+                    .into_iter()
+                    .map(move |(image, next_delta)| (image.map(|x| x - delta), delta + next_delta))
+            });
+
+        Self::new(pieces).expect("pieces of a composition should not overlap")
+    }
+
+    /// Fails if this map is not a bijection, i.e. if its shifted ranges do not exactly cover the
+    /// ranges they came from.
+    pub fn inverse(&self) -> anyhow::Result<Self> {
+        let mut sources: RangeSet<i64> = RangeSet::new();
+        let mut images: RangeSet<i64> = RangeSet::new();
+
+        for &(range, delta) in &self.pieces {
+            sources.insert(range);
+            images.insert(range.map(|x| x + delta));
+        }
+
+        if !sources.ranges().eq(images.ranges()) {
+            bail!("map is not invertible: {sources:?} is sent to {images:?}");
+        }
+
+        Self::new(
+            self.pieces
+                .iter()
+                .map(|&(range, delta)| (range.map(|x| x + delta), -delta)),
+        )
+        .context("map is not invertible: shifted ranges overlap")
     }
 
-    *seeds.min().unwrap()
+    /// The image of every number in `set` under this map.
+    pub fn apply(&self, set: &RangeSet<i64>) -> RangeSet<i64> {
+        let mut res = RangeSet::new();
+
+        for &range in set.ranges() {
+            for (piece, delta) in self.split(range) {
+                res.insert(piece.map(|x| x + delta));
+            }
+        }
+
+        res
+    }
 }
 
-fn solve_a(input: &str) -> i64 {
-    let mut pars = input.split("\n\n");
+/// A parsed almanac: the seeds, and a graph of categories whose edges are the sections converting
+/// between them.
+#[derive(Clone, Debug)]
+pub struct Almanac<'a> {
+    pub seeds: Vec<i64>,
 
-    let mut seeds = ints::<i64>(pars.next().unwrap()).vec();
+    /// Keyed by source category. The values are the target category and the conversion to it.
+    maps: HashMap<&'a str, (&'a str, PiecewiseMap)>,
+}
 
-    for par in pars {
-        let map = par
-            .lines()
-            .skip(1)
-            .map(|line| {
-                let ns = ints::<i64>(line).vec();
-                (ns[1]..(ns[1] + ns[2]), ns[0] - ns[1])
-            })
-            .vec();
+impl<'a> Almanac<'a> {
+    /// Sections may come in any order, but every category may be converted from at most once, and
+    /// the sections must link up into one chain.
+    pub fn parse(input: &'a str) -> anyhow::Result<Self> {
+        let mut pars = input.split("\n\n");
+
+        let seeds_line = pars.next().context("almanac should not be empty")?;
+        let seeds = ints::<i64>(seeds_line).vec();
+
+        let mut maps = HashMap::new();
 
-        for seed in &mut seeds {
-            if let Some((range, delta)) = map.iter().find(|(range, _)| range.contains(&seed)) {
-                *seed += delta;
+        for par in pars {
+            let (header, map) = par.split_once('\n').unwrap_or((par, ""));
+
+            let (source, target) = header
+                .strip_suffix(" map:")
+                .and_then(|name| name.split_once("-to-"))
+                .with_context(|| format!("invalid section header '{header}'"))?;
+
+            let map = map
+                .parse::<PiecewiseMap>()
+                .with_context(|| format!("invalid section '{header}'"))?;
+
+            if let Some((prev_target, _)) = maps.insert(source, (target, map)) {
+                bail!("'{source}' is converted to both '{prev_target}' and '{target}'");
             }
         }
+
+        let almanac = Self { seeds, maps };
+
+        let chain = almanac.chain()?;
+        debug!("category chain: {}", chain.join(" -> "));
+
+        Ok(almanac)
+    }
+
+    /// Every category, in the order that the sections convert between them. Fails if the sections
+    /// do not form a single chain.
+    pub fn chain(&self) -> anyhow::Result<Vec<&'a str>> {
+        let targets: HashSet<&str> = self.maps.values().map(|(target, _)| *target).collect();
+
+        let Ok(&first) = self
+            .maps
+            .keys()
+            .filter(|source| !targets.contains(*source))
+            .exactly_one()
+        else {
+            bail!("sections should form a chain with exactly one starting category");
+        };
+
+        let mut chain = vec![first];
+
+        while let Some((target, _)) = self.maps.get(chain.last().unwrap()) {
+            if chain.contains(target) {
+                bail!("sections convert '{target}' in a cycle");
+            }
+
+            chain.push(target);
+        }
+
+        if chain.len() != self.maps.len() + 1 {
+            bail!("sections do not all connect to the chain starting at '{first}'");
+        }
+
+        Ok(chain)
+    }
+
+    /// The composition of every section on the way from `source` to `target`.
+    pub fn conversion(&self, source: &str, target: &str) -> anyhow::Result<PiecewiseMap> {
+        let mut map = PiecewiseMap::identity();
+        let mut category = source;
+
+        while category != target {
+            let (next, next_map) = self
+                .maps
+                .get(category)
+                .with_context(|| format!("no conversion from '{source}' to '{target}'"))?;
+
+            map = map.then(next_map);
+            category = next;
+
+            if category == source {
+                bail!("no conversion from '{source}' to '{target}'");
+            }
+        }
+
+        Ok(map)
     }
+}
+
+fn seed_to_location(almanac: &Almanac) -> PiecewiseMap {
+    let seed_to_location = almanac
+        .conversion("seed", "location")
+        .expect("almanac should convert seeds to locations");
 
-    *seeds.iter().min().unwrap()
+    debug!("seed to location: {seed_to_location:?}");
+
+    seed_to_location
+}
+
+fn solve_b(input: &str) -> i64 {
+    let almanac = Almanac::parse(input).expect("almanac should parse");
+
+    let mut seed_ranges: RangeSet<i64> = RangeSet::new();
+    for [&a, &b] in almanac.seeds.iter().util_array_chunks() {
+        seed_ranges.insert(Range::new(a, a + b - 1));
+    }
+
+    *seed_to_location(&almanac)
+        .apply(&seed_ranges)
+        .min()
+        .unwrap()
+}
+
+fn solve_a(input: &str) -> i64 {
+    let almanac = Almanac::parse(input).expect("almanac should parse");
+
+    let seed_to_location = seed_to_location(&almanac);
+
+    almanac
+        .seeds
+        .iter()
+        .map(|&seed| seed_to_location.get(seed))
+        .min()
+        .unwrap()
 }
 
 pub fn solve<P: AocPart>(input: &str) -> i64 {
@@ -124,6 +347,36 @@ example_tests! {
         b0: EXAMPLE_INPUT => 46,
 }
 
+#[test]
+fn example_inverse() {
+    let almanac = Almanac::parse(EXAMPLE_INPUT).unwrap();
+    let seed_to_location = almanac.conversion("seed", "location").unwrap();
+    let location_to_seed = seed_to_location.inverse().unwrap();
+
+    for (seed, location) in [(79, 82), (14, 43), (55, 86), (13, 35)] {
+        assert_eq!(seed_to_location.get(seed), location);
+        assert_eq!(location_to_seed.get(location), seed);
+    }
+}
+
+#[test]
+fn example_shuffled_sections() {
+    let (seeds, sections) = EXAMPLE_INPUT.split_once("\n\n").unwrap();
+    let shuffled = iter::once(seeds)
+        .chain(sections.rsplit("\n\n"))
+        .join("\n\n");
+
+    assert_eq!(solve::<part::One>(&shuffled), 35);
+
+    let almanac = Almanac::parse(&shuffled).unwrap();
+    assert_eq!(almanac.chain().unwrap().first(), Some(&"seed"));
+    assert_eq!(almanac.conversion("soil", "humidity").unwrap().get(81), 78);
+    assert!(almanac.conversion("humidity", "soil").is_err());
+
+    let broken = EXAMPLE_INPUT.replace("water-to-light", "water-to-lamp");
+    assert!(Almanac::parse(&broken).is_err());
+}
+
 #[test]
 fn part_one() {
     let _ = aocutil::log::test_subscriber().try_init();
@@ -4,44 +4,62 @@ const YEAR: usize = 2023;
 
 const DAY: usize = 6;
 
-#[instrument(level = "trace")]
-fn num_ways_to_win(race_time: f64, best_distance: f64) -> u128 {
-    let vertex_time = race_time / 2.0;
-    let discriminant = race_time.powi(2) - 4.0 * best_distance;
-    let res = if discriminant < 0.0 {
-        0
-    } else {
-        let pm = -discriminant.sqrt() / 2.0;
-        let lower_bound = cmp::max(0, (vertex_time + pm + 1.0).floor() as u128);
-        let upper_bound = (vertex_time - pm - 1.0).ceil() as u128;
-        trace!("{lower_bound} .. {upper_bound}");
-        upper_bound - lower_bound + 1
-    };
-    trace!("  {res}");
-    res
+/// The range of hold times which beat `best_distance` in a race lasting `race_time`, if any.
+#[instrument(level = "trace", ret)]
+fn winning_hold_times(race_time: u64, best_distance: u128) -> Option<Range<u64>> {
+    let distance = |hold: u64| u128::from(hold) * u128::from(race_time - hold);
+
+    // the distance is symmetric about `race_time / 2`, and increasing up to it
+    let vertex_time = race_time / 2;
+    if distance(vertex_time) <= best_distance {
+        return None;
+    }
+
+    // binary search for the shortest winning hold
+    let (mut lower_bound, mut upper_bound) = (0, vertex_time);
+    while lower_bound < upper_bound {
+        let mid = lower_bound + (upper_bound - lower_bound) / 2;
+        if distance(mid) > best_distance {
+            upper_bound = mid;
+        } else {
+            lower_bound = mid + 1;
+        }
+    }
+
+    Some(Range::new(lower_bound, race_time - lower_bound))
+}
+
+fn num_ways_to_win(race_time: u64, best_distance: u128) -> u128 {
+    winning_hold_times(race_time, best_distance).map_or(0, |hold_times| {
+        u128::from(hold_times.end - hold_times.start + 1)
+    })
+}
+
+/// Reads all of the digits on a line as one number, ignoring the spaces between them.
+fn kerned_number<T: FromStr>(line: &str) -> T
+where
+    T::Err: Debug,
+{
+    line.chars()
+        .filter(char::is_ascii_digit)
+        .collect::<String>()
+        .parse()
+        .expect("line should contain a number")
 }
 
 fn solve<Part: AocPart>(input: &str) -> u128 {
     let mut lines = input.lines();
 
     if Part::is_one() {
-        let race_times = ints::<i32>(lines.next().unwrap())
-            .map(f64::from)
-            .collect_vec();
-        let best_distances = ints::<i32>(lines.next().unwrap())
-            .map(f64::from)
-            .collect_vec();
+        let race_times = ints::<u64>(lines.next().unwrap()).collect_vec();
+        let best_distances = ints::<u64>(lines.next().unwrap()).collect_vec();
         iter::zip(race_times, best_distances)
-            .map(|(race_time, best_distance)| num_ways_to_win(race_time, best_distance))
+            .map(|(race_time, best_distance)| num_ways_to_win(race_time, best_distance.into()))
             .product()
     } else {
-        let race_time: f64 = ints::<u64>(&lines.next().unwrap().replace(" ", ""))
-            .next()
-            .unwrap() as f64;
+        let race_time: u64 = kerned_number(lines.next().unwrap());
 
-        let best_distance: f64 = ints::<u64>(&lines.next().unwrap().replace(" ", ""))
-            .next()
-            .unwrap() as f64;
+        let best_distance: u128 = kerned_number(lines.next().unwrap());
 
         num_ways_to_win(race_time, best_distance)
     }
@@ -57,6 +75,25 @@ example_tests! {
         b0: EXAMPLE_INPUT => 71503,
 }
 
+#[test]
+fn exact_hold_times() {
+    // the only winning hold is a perfect square away from the record
+    assert_eq!(winning_hold_times(10, 24), Some(Range::new(5, 5)));
+    assert_eq!(winning_hold_times(10, 25), None);
+
+    // far beyond the precision of an `f64`
+    let half = 1 << 61;
+    let best_distance = u128::from(half) * u128::from(half) - 1;
+    assert_eq!(
+        winning_hold_times(half * 2, best_distance),
+        Some(Range::new(half, half))
+    );
+    assert_eq!(
+        winning_hold_times(half * 2, best_distance - 3),
+        Some(Range::new(half - 1, half + 1))
+    );
+}
+
 #[test]
 fn part_one() {
     let _ = aocutil::log::test_subscriber().try_init();
@@ -1,19 +1,9 @@
-use std::cmp::Ordering;
-
 use aocutil::prelude::*;
 
 pub const YEAR: usize = 2023;
 
 pub const DAY: usize = 7;
 
-const CARD_ORDER_A: [char; 13] = [
-    '2', '3', '4', '5', '6', '7', '8', '9', 'T', 'J', 'Q', 'K', 'A',
-];
-
-const CARD_ORDER_B: [char; 13] = [
-    'J', '2', '3', '4', '5', '6', '7', '8', '9', 'T', 'Q', 'K', 'A',
-];
-
 #[derive(PartialEq, Eq, Copy, Clone, Hash)]
 struct Card(char);
 
@@ -23,111 +13,235 @@ impl Debug for Card {
     }
 }
 
-impl Card {
-    fn rank<P: AocPart>(&self) -> usize {
-        match P::part() {
-            Part::One => CARD_ORDER_A,
-            Part::Two => CARD_ORDER_B,
+/// The kind of a hand, weakest first. Hands are compared by type before their cards are compared.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum HandType {
+    HighCard,
+    OnePair,
+    TwoPair,
+    ThreeOfAKind,
+    FullHouse,
+    FourOfAKind,
+    FiveOfAKind,
+    /// Six or more of a kind, which is only possible with more than five cards in a hand.
+    ManyOfAKind(usize),
+}
+
+impl HandType {
+    /// Increases with the strength of the type.
+    fn ordinal(self) -> u64 {
+        match self {
+            Self::HighCard => 0,
+            Self::OnePair => 1,
+            Self::TwoPair => 2,
+            Self::ThreeOfAKind => 3,
+            Self::FullHouse => 4,
+            Self::FourOfAKind => 5,
+            Self::FiveOfAKind => 6,
+            Self::ManyOfAKind(n) => n as u64 + 1,
         }
-        .iter()
-        .position(|c| *c == self.0)
-        .expect("card should be valid")
     }
 
-    fn cmp<Part: AocPart>(&self, other: &Self) -> cmp::Ordering {
-        self.rank::<Part>().cmp(&other.rank::<Part>())
+    /// Classifies a hand by the counts of its two most common cards.
+    fn from_counts(greater: usize, lower: usize) -> Self {
+        match (greater, lower) {
+            (n, _) if n >= 6 => Self::ManyOfAKind(n),
+            (5, _) => Self::FiveOfAKind,
+            (4, _) => Self::FourOfAKind,
+            (3, 2..) => Self::FullHouse,
+            (3, _) => Self::ThreeOfAKind,
+            (2, 2..) => Self::TwoPair,
+            (2, _) => Self::OnePair,
+            _ => Self::HighCard,
+        }
     }
 }
 
-#[derive(PartialEq, Eq, Clone, Copy)]
-struct Hand {
-    cards: [Card; 5],
+/// A variant of the rules of Camel Cards.
+#[derive(Clone, Debug)]
+pub struct CamelRules {
+    /// Every valid card, weakest first.
+    pub card_order: Vec<char>,
+
+    /// Cards which act like whichever card makes the hand's type strongest. They still use their
+    /// own place in `card_order` when breaking ties.
+    pub wild: Vec<char>,
+
+    /// The number of cards in every hand.
+    pub hand_size: usize,
 }
 
-impl Debug for Hand {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        for card in self.cards {
-            f.write_char(card.0)?;
+impl CamelRules {
+    /// The rules from part one.
+    pub fn standard() -> Self {
+        Self {
+            card_order: "23456789TJQKA".chars().collect(),
+            wild: Vec::new(),
+            hand_size: 5,
         }
-        Ok(())
     }
-}
 
-impl FromStr for Hand {
-    type Err = anyhow::Error;
+    /// The rules from part two: `J` is a joker, which is wild but the weakest card.
+    pub fn jokers() -> Self {
+        Self {
+            card_order: "J23456789TQKA".chars().collect(),
+            wild: vec!['J'],
+            hand_size: 5,
+        }
+    }
+
+    pub fn for_part<P: AocPart>() -> Self {
+        match P::part() {
+            Part::One => Self::standard(),
+            Part::Two => Self::jokers(),
+        }
+    }
+
+    fn card_rank(&self, card: Card) -> usize {
+        self.card_order
+            .iter()
+            .position(|c| *c == card.0)
+            .expect("card should be valid")
+    }
+
+    /// Parses a hand, working out its type and strength up front so that sorting hands never has
+    /// to look at their cards.
+    pub fn parse_hand(&self, s: &str) -> anyhow::Result<Hand> {
+        let cards: Vec<Card> = s.chars().map(Card).collect();
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        if s.len() != 5 {
-            bail!("expected hand length 5, got length {}", s.len());
+        if cards.len() != self.hand_size {
+            bail!(
+                "expected hand length {}, got length {}",
+                self.hand_size,
+                cards.len()
+            );
         }
 
-        Ok(Self {
-            cards: s.chars().fill_array().unwrap().map(Card),
+        if let Some(invalid) = cards.iter().find(|c| !self.card_order.contains(&c.0)) {
+            bail!("invalid card {invalid:?} in hand {s}");
+        }
+
+        let hand_type = self.hand_type(&cards);
+
+        // the type, followed by the rank of every card as digits in base `card_order.len()`
+        let radix = self.card_order.len() as u64;
+        let strength = cards
+            .iter()
+            .try_fold(hand_type.ordinal(), |strength, &card| {
+                strength
+                    .checked_mul(radix)?
+                    .checked_add(self.card_rank(card) as u64)
+            })
+            .with_context(|| format!("hand {s} is too large to rank"))?;
+
+        Ok(Hand {
+            cards,
+            hand_type,
+            strength,
         })
     }
-}
 
-impl Hand {
-    /// returns the counts of the two most common cards in this hand: (greater, lower)
-    #[instrument(level = "trace", ret)]
-    fn hand_type<Part: AocPart>(&self) -> (usize, usize) {
-        let mut card_counts: HashMap<Card, usize> = self.cards.into_iter().counts();
-
-        if Part::is_two() {
-            if let Some(j_count) = card_counts.remove(&Card('J')) {
-                let Some((high_count_card, _)) = card_counts.iter().max_by_key(|(_, count)| *count)
-                else {
-                    return (5, 0);
-                };
-
-                *card_counts.entry(*high_count_card).or_insert(0) += j_count;
-            }
-        }
+    fn hand_type(&self, cards: &[Card]) -> HandType {
+        let mut card_counts: HashMap<Card, usize> = cards.iter().copied().counts();
+
+        let wild_count: usize = self
+            .wild
+            .iter()
+            .filter_map(|&c| card_counts.remove(&Card(c)))
+            .sum();
 
         let mut counts: Vec<usize> = card_counts.into_values().collect();
         counts.sort();
 
-        (
-            *counts
-                .last()
-                .expect("there is at least one card in the hand"),
-            if counts.len() >= 2 {
-                counts[counts.len() - 2]
-            } else {
-                0
-            },
-        )
+        // wild cards are best spent all on the most common card
+        let greater = counts.pop().unwrap_or(0) + wild_count;
+        let lower = counts.pop().unwrap_or(0);
+
+        HandType::from_counts(greater, lower)
+    }
+
+    /// Parses a list of hands and bids.
+    pub fn parse_game(&self, input: &str) -> anyhow::Result<Vec<(Hand, u32)>> {
+        input
+            .lines()
+            .map(|line| {
+                let (hand, bid) = line.split_once(' ').context("line should have a space")?;
+                let hand = self.parse_hand(hand)?;
+                let bid: u32 = bid.parse().context("bid should be a number")?;
+                Ok((hand, bid))
+            })
+            .collect()
     }
 
-    #[instrument(level = "trace", ret)]
-    fn cmp<Part: AocPart>(&self, other: &Self) -> cmp::Ordering {
-        let type_ord = self.hand_type::<Part>().cmp(&other.hand_type::<Part>());
-        let lexi_ord = iter::zip(self.cards, other.cards)
-            .fold(Ordering::Equal, |ord, (c, d)| ord.then(c.cmp::<Part>(&d)));
-        type_ord.then(lexi_ord)
+    /// The sum of each hand's bid times its rank, for a list of hands and bids.
+    pub fn total_winnings(&self, input: &str) -> anyhow::Result<u64> {
+        Ok(rank_hands(self.parse_game(input)?)
+            .iter()
+            .map(|ranked| ranked.winnings)
+            .sum())
     }
 }
 
-pub fn solve<Part: AocPart>(input: &str) -> u32 {
-    let mut hands: Vec<(Hand, u32)> = input
-        .lines()
-        .map(|line| {
-            let (hand, bid) = line.split_once(' ').expect("line should parse");
-            let hand: Hand = hand.parse().expect("hand should parse");
-            let bid: u32 = bid.parse().expect("bid should parse");
-            (hand, bid)
-        })
-        .collect();
+#[derive(PartialEq, Eq, Clone)]
+pub struct Hand {
+    cards: Vec<Card>,
+    hand_type: HandType,
+
+    /// Orders hands the same way as comparing their types, then their cards.
+    strength: u64,
+}
+
+impl Debug for Hand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for card in &self.cards {
+            f.write_char(card.0)?;
+        }
+        Ok(())
+    }
+}
+
+impl Hand {
+    pub fn hand_type(&self) -> HandType {
+        self.hand_type
+    }
+
+    pub fn strength(&self) -> u64 {
+        self.strength
+    }
+}
 
-    hands.sort_by(|(l_hand, _), (r_hand, _)| l_hand.cmp::<Part>(r_hand));
+/// A hand's place in a game, after every hand has been ranked.
+#[derive(Clone, Debug)]
+pub struct RankedHand {
+    /// 1 for the weakest hand.
+    pub rank: usize,
+    pub hand: Hand,
+    pub bid: u32,
+    pub winnings: u64,
+}
+
+/// Sorts hands weakest first and works out what each of them wins.
+pub fn rank_hands(mut hands: Vec<(Hand, u32)>) -> Vec<RankedHand> {
+    hands.sort_unstable_by_key(|(hand, _)| hand.strength);
 
     trace!("{hands:?}");
 
     hands
         .into_iter()
         .enumerate()
-        .map(|(rank, (hand, bid))| (rank + 1) as u32 * bid)
-        .sum()
+        .map(|(idx, (hand, bid))| RankedHand {
+            rank: idx + 1,
+            hand,
+            bid,
+            winnings: (idx as u64 + 1) * u64::from(bid),
+        })
+        .collect()
+}
+
+pub fn solve<Part: AocPart>(input: &str) -> u64 {
+    CamelRules::for_part::<Part>()
+        .total_winnings(input)
+        .expect("input should parse")
 }
 
 const EXAMPLE_INPUT: &str = "32T3K 765
@@ -143,6 +257,37 @@ example_tests! {
         b0: EXAMPLE_INPUT => 5905,
 }
 
+#[test]
+fn variant_rules() {
+    let rules = CamelRules::jokers();
+    let ranked = rank_hands(rules.parse_game(EXAMPLE_INPUT).unwrap());
+    let hand_types = ranked
+        .iter()
+        .map(|ranked| ranked.hand.hand_type())
+        .collect_vec();
+    assert_eq!(
+        hand_types,
+        [
+            HandType::OnePair,
+            HandType::TwoPair,
+            HandType::FourOfAKind,
+            HandType::FourOfAKind,
+            HandType::FourOfAKind,
+        ]
+    );
+
+    let rules = CamelRules {
+        wild: vec!['J', '2'],
+        hand_size: 7,
+        ..CamelRules::jokers()
+    };
+    let hand = rules.parse_hand("AJ2A2KK").unwrap();
+    assert_eq!(hand.hand_type(), HandType::FiveOfAKind);
+    let hand = rules.parse_hand("AJ2A2AK").unwrap();
+    assert_eq!(hand.hand_type(), HandType::ManyOfAKind(6));
+    assert!(rules.parse_hand("AJ2A2").is_err());
+}
+
 #[test]
 fn part_one() {
     let _ = aocutil::log::test_subscriber().try_init();
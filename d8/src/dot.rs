@@ -0,0 +1,122 @@
+use super::*;
+
+/// Edge colors for ghost orbits, reused if there are more ghosts than colors.
+const ORBIT_COLORS: [&str; 6] = ["red", "blue", "darkgreen", "orange", "purple", "brown"];
+
+/// `id` as a quoted DOT ID, so that names can contain any character.
+fn quoted(id: impl Display) -> String {
+    let escaped = id.to_string().replace('\\', "\\\\").replace('"', "\\\"");
+    format!("\"{escaped}\"")
+}
+
+/// Renders `network` in Graphviz DOT format, with the ghost start nodes of `rules` filled green
+/// and its ghost end nodes filled red.
+///
+/// If `with_orbits` is set, the edges that each ghost walks are drawn in its color: dashed if it
+/// only walks them before its orbit starts repeating, and bold if they are part of its cycle.
+pub fn network_dot(network: &Network, rules: &NetworkRules, with_orbits: bool) -> String {
+    let starts = network.mask(&rules.ghost_start);
+    let ends = network.mask(&rules.ghost_end);
+
+    // for each edge, the colors of the ghosts that walk it, and whether any of them walk it as part
+    // of their cycle
+    let mut walked: HashMap<(Node, Step), (Vec<&str>, bool)> = HashMap::new();
+
+    if with_orbits {
+        let ghosts = network.nodes().filter(|node| starts.contains(*node));
+
+        for (start, color) in ghosts.zip(ORBIT_COLORS.iter().cycle()) {
+            let orbit = Orbit::from_iter(&network.steps, iterate_sequence(network, start));
+
+            debug!(
+                "node {} ({color}): {}",
+                network.name(start),
+                orbit.display(network)
+            );
+
+            for (i, &(t, node)) in orbit.nodes.iter().enumerate() {
+                let step = network.steps[t % network.steps.len()];
+                let (colors, in_cycle) = walked.entry((node, step)).or_default();
+
+                if !colors.contains(color) {
+                    colors.push(color);
+                }
+
+                *in_cycle |= i >= orbit.cycle_start;
+            }
+        }
+    }
+
+    let mut dot = String::from("digraph network {\n");
+
+    for node in network.nodes() {
+        let fill = if starts.contains(node) {
+            "palegreen"
+        } else if ends.contains(node) {
+            "lightcoral"
+        } else {
+            continue;
+        };
+
+        let name = quoted(network.name(node));
+        writeln!(dot, "    {name} [style=filled, fillcolor={fill}];").unwrap();
+    }
+
+    for node in network.nodes() {
+        for (branch, &dst) in network.branches(node).iter().enumerate() {
+            let step = Step::new(branch);
+
+            write!(
+                dot,
+                "    {} -> {} [label={}",
+                quoted(network.name(node)),
+                quoted(network.name(dst)),
+                quoted(network.step_char(step))
+            )
+            .unwrap();
+
+            if let Some((colors, in_cycle)) = walked.get(&(node, step)) {
+                let style = if *in_cycle { "bold" } else { "dashed" };
+                write!(dot, ", color=\"{}\", style={style}", colors.join(":")).unwrap();
+            }
+
+            dot.push_str("];\n");
+        }
+    }
+
+    dot.push_str("}\n");
+
+    dot
+}
+
+#[test]
+fn example_dot() {
+    let rules = NetworkRules::default();
+    let network = Network::parse(
+        "LR
+
+11A = (11B, XXX)
+11B = (XXX, 11Z)
+11Z = (11B, XXX)
+XXX = (XXX, XXX)",
+        &rules,
+    )
+    .unwrap();
+    let dot = network_dot(&network, &rules, true);
+
+    assert!(dot.contains("\"11A\" [style=filled, fillcolor=palegreen];"));
+    assert!(dot.contains("\"11Z\" [style=filled, fillcolor=lightcoral];"));
+    assert!(dot.contains("\"11A\" -> \"11B\" [label=\"L\", color=\"red\", style=dashed];"));
+    assert!(dot.contains("\"11B\" -> \"11Z\" [label=\"R\", color=\"red\", style=bold];"));
+    assert!(dot.contains("\"11A\" -> \"XXX\" [label=\"R\"];"));
+
+    // quotes and backslashes in names and instructions are escaped
+    let rules = NetworkRules {
+        alphabet: vec!['"'],
+        ..NetworkRules::default()
+    };
+    let network = Network::parse("\"\n\nx\"A = (x\\A)\nx\\A = (x\"A)", &rules).unwrap();
+    let dot = network_dot(&network, &rules, false);
+    assert!(dot.contains(r#""x\"A" -> "x\\A" [label="\""];"#));
+    assert!(dot.contains(r#""x\\A" -> "x\"A" [label="\""];"#));
+}
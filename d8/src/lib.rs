@@ -1,88 +1,49 @@
+mod dot;
+mod network;
+mod periodic;
+
 use aocutil::prelude::*;
 
+pub use dot::network_dot;
+pub use network::{Network, NetworkRules, Node, NodeMask, NodePattern, Step};
+pub use periodic::PeriodicSet;
+
 pub const YEAR: usize = 2023;
 
 pub const DAY: usize = 8;
 
-fn solve_a(steps: &[Step], nodes: HashMap<Node, (Node, Node)>) -> usize {
-    let mut node = Node::aaa();
-    for (idx, step) in steps.iter().cycle().enumerate() {
-        let (l, r) = nodes[&node];
+fn solve_a(network: &Network, rules: &NetworkRules) -> anyhow::Result<usize> {
+    let end = network.mask(&rules.end);
 
-        match step {
-            Step::Left => node = l,
-            Step::Right => node = r,
-        }
+    let starts = network
+        .nodes()
+        .filter(|node| rules.start.matches(network.name(*node)))
+        .collect_vec();
 
-        if node.is_zzz() {
-            return idx + 1;
-        }
-    }
-
-    unreachable!()
-}
-
-#[derive(Copy, Clone, Hash, Debug, PartialEq, Eq)]
-enum Step {
-    Left,
-    Right,
-}
-
-impl Step {
-    fn from_char(c: char) -> Self {
-        match c {
-            'L' => Self::Left,
-            'R' => Self::Right,
-            other => panic!("step should be L or R, found {other}"),
-        }
-    }
-}
-
-#[derive(Copy, Clone, Hash, Debug, PartialEq, Eq)]
-struct Node {
-    name: [char; 3],
-}
-
-impl Display for Node {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        for c in self.name {
-            f.write_char(c)?;
-        }
-        Ok(())
-    }
-}
+    let [mut node] = starts[..] else {
+        bail!(
+            "start pattern {:?} should match exactly one node, but matches {}",
+            rules.start.0,
+            starts.len()
+        );
+    };
 
-impl FromStr for Node {
-    type Err = anyhow::Error;
+    // the walk is at the same node on the same instruction again once it has taken every
+    // instruction from every node, so if it hasn't reached an end by then it never will
+    let max_steps = network.nodes().count() * network.steps.len();
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        Ok(Self {
-            name: s
-                .chars()
-                .fill_array::<3>()
-                .context("node names should be three letters")?,
-        })
-    }
-}
+    for (idx, &step) in network.steps.iter().cycle().enumerate().take(max_steps) {
+        node = network.next(node, step);
 
-impl Node {
-    fn aaa() -> Self {
-        Self {
-            name: ['A', 'A', 'A'],
+        if end.contains(node) {
+            return Ok(idx + 1);
         }
     }
 
-    fn is_zzz(&self) -> bool {
-        self.name == ['Z', 'Z', 'Z']
-    }
-
-    fn is_z(&self) -> bool {
-        self.name[2] == 'Z'
-    }
-
-    fn is_a(&self) -> bool {
-        self.name[2] == 'A'
-    }
+    bail!(
+        "the walk from {} never reaches an end",
+        network.name(starts[0])
+    )
 }
 
 /// All the information you need to store any infinite sequence of nodes, as they must repeat.
@@ -100,59 +61,62 @@ struct Orbit {
     cycle_step: usize,
 }
 
-impl Display for Orbit {
+/// Displays an orbit with the names of its nodes.
+struct OrbitDisplay<'a> {
+    orbit: &'a Orbit,
+    network: &'a Network,
+}
+
+impl Display for OrbitDisplay<'_> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        for (t, node) in &self.nodes[..self.cycle_start] {
-            write!(f, "(t={t} {node}) ")?;
+        let Self { orbit, network } = self;
+
+        for &(t, node) in &orbit.nodes[..orbit.cycle_start] {
+            write!(f, "(t={t} {}) ", network.name(node))?;
         }
 
         write!(f, "r[")?;
 
-        for (t, node) in &self.nodes[self.cycle_start..] {
-            write!(f, "(t={t} {node}) ")?;
+        for &(t, node) in &orbit.nodes[orbit.cycle_start..] {
+            write!(f, "(t={t} {}) ", network.name(node))?;
         }
 
         write!(f, "]")?;
 
-        write!(f, " step: {}", self.cycle_step)?;
+        write!(f, " step: {}", orbit.cycle_step)?;
 
         Ok(())
     }
 }
 
 impl Orbit {
-    fn cycle_length(&self) -> usize {
-        self.nodes.len() - self.cycle_start
+    fn display<'a>(&'a self, network: &'a Network) -> OrbitDisplay<'a> {
+        OrbitDisplay {
+            orbit: self,
+            network,
+        }
     }
 
-    fn into_iter(&self) -> impl Iterator<Item = (usize, Node)> + '_ {
-        self.nodes[0..self.cycle_start].iter().copied().chain(
-            (0..)
-                .map(move |cycle| {
-                    self.nodes[self.cycle_start..]
-                        .iter()
-                        .copied()
-                        .map(move |(step, node)| (step + cycle * self.cycle_step, node))
-                })
-                .flatten(),
+    /// The steps on which this orbit is at one of the nodes in `ends`.
+    fn end_steps(&self, ends: &NodeMask) -> PeriodicSet {
+        let (threshold, _) = self.nodes[self.cycle_start];
+
+        let (prefix, cycle) = self.nodes.split_at(self.cycle_start);
+
+        PeriodicSet::new(
+            prefix
+                .iter()
+                .filter(|(_, node)| ends.contains(*node))
+                .map(|(t, _)| *t),
+            threshold,
+            self.cycle_step,
+            cycle
+                .iter()
+                .filter(|(_, node)| ends.contains(*node))
+                .map(|(t, _)| *t),
         )
     }
 
-    /// Fast-forward to the nth entry in the sequence (not the value at the nth global step).
-    fn nth(&self, n: usize) -> (usize, Node) {
-        if n < self.cycle_step {
-            self.nodes[n]
-        } else {
-            let (t, node) =
-                self.nodes[self.cycle_start + (n - self.cycle_start) % self.cycle_length()];
-
-            (
-                t + self.cycle_step * ((n - self.cycle_start) / self.cycle_length()),
-                node,
-            )
-        }
-    }
-
     /// Takes as input:
     /// - the sequence of global L/R steps
     /// - an iterator of pairs of un-modded step numbers and nodes
@@ -169,7 +133,7 @@ impl Orbit {
         let mut nodes: Vec<(usize, Node)> = Vec::new();
 
         for (t, node) in nodes_iter {
-            trace!("got (t={t} {node})");
+            trace!("got (t={t} {node:?})");
 
             if t < prev_max_t {
                 panic!("t should be strictly increasing");
@@ -194,113 +158,67 @@ impl Orbit {
     }
 }
 
-fn iterate_sequence<'a>(
-    steps: &'a [Step],
-    nodes: &'a HashMap<Node, (Node, Node)>,
-    mut node: Node,
-) -> impl Iterator<Item = (usize, Node)> + 'a {
-    steps.iter().cycle().enumerate().map(move |(t, step)| {
-        let res = (t, node);
-
-        let &(l, r) = nodes.get(&node).expect("node should be in map");
-        match step {
-            Step::Left => node = l,
-            Step::Right => node = r,
-        }
-
-        res
-    })
-}
-
-fn solve_b(steps: &[Step], nodes: &HashMap<Node, (Node, Node)>) -> usize {
-    let mut a_node_orbits: HashMap<Node, Orbit> = HashMap::new();
+fn iterate_sequence(network: &Network, mut node: Node) -> impl Iterator<Item = (usize, Node)> + '_ {
+    network
+        .steps
+        .iter()
+        .cycle()
+        .enumerate()
+        .map(move |(t, &step)| {
+            let res = (t, node);
 
-    // STEP 1: find the orbits of all A nodes so that finding their nth iterates is trivial
+            node = network.next(node, step);
 
-    for &a_node in nodes.keys().filter(|node| node.is_a()) {
-        let orbit = Orbit::from_iter(steps, iterate_sequence(steps, nodes, a_node));
-
-        debug!("node {a_node}: {orbit}");
-
-        a_node_orbits.insert(a_node, orbit);
-    }
-
-    // STEP 2: look at the orbits of successive A nodes under the sequences of Z nodes of the
-    // previous node.
-
-    let mut a_nodes = nodes.keys().filter(|node| node.is_a());
-
-    let first_a_node = *a_nodes.next().expect("there is at least one A node");
-    trace!("starting with {first_a_node}");
-
-    let mut current_orbit = a_node_orbits[&first_a_node].clone();
+            res
+        })
+}
 
-    for a_node in a_nodes {
-        trace!("looking at {a_node}");
-        // trace!("current orbit: {current_orbit}");
+fn solve_b(network: &Network, rules: &NetworkRules) -> anyhow::Result<usize> {
+    let ghost_ends = network.mask(&rules.ghost_end);
 
-        let a_node_orbit = &a_node_orbits[a_node];
+    // every ghost's orbit is eventually periodic, so the steps on which it is at an end are too
+    let end_steps = network
+        .nodes()
+        .filter(|node| rules.ghost_start.matches(network.name(*node)))
+        .map(|start| {
+            let orbit = Orbit::from_iter(&network.steps, iterate_sequence(network, start));
 
-        trace!(
-            "current_orbit cycle_start: {}, cycle_step: {}",
-            current_orbit.cycle_start,
-            current_orbit.cycle_step
-        );
+            debug!("node {}: {}", network.name(start), orbit.display(network));
 
-        current_orbit = Orbit::from_iter(
-            steps,
-            current_orbit
-                .into_iter()
-                .filter(|(t, node)| {
-                    if node.is_z() {
-                        trace!("found z node in current orbit: t={t} {node}");
-                    }
-                    node.is_z()
-                })
-                .map(|(t, _)| {
-                    let (u, res) = a_node_orbit.nth(t);
-                    trace!("{t}th {a_node} iterate: t={u} {res}");
-                    (u, res)
-                }),
-        );
-    }
+            orbit.end_steps(&ghost_ends)
+        });
 
-    // STEP 3: find the first Z node in the remaining orbit
+    let all_end_steps = end_steps
+        .reduce(|all, ghost| all.intersection(&ghost))
+        .with_context(|| {
+            format!(
+                "ghost start pattern {:?} should match at least one node",
+                rules.ghost_start.0
+            )
+        })?;
 
-    let (first_z_step, _) = current_orbit
-        .into_iter()
-        .find(|(_, node)| node.is_z())
-        .expect("orbits should be infinite");
+    debug!("common end steps: {all_end_steps:?}");
 
-    first_z_step
+    all_end_steps
+        .first()
+        .context("ghosts are never all at ends at the same time")
 }
 
-pub fn solve<Part: AocPart>(input: &str) -> usize {
-    let mut lines = input.lines();
-
-    let steps: Vec<Step> = lines.next().unwrap().chars().map(Step::from_char).collect();
-
-    let nodes: HashMap<Node, (Node, Node)> = lines
-        .skip(1)
-        .map(|line| {
-            unlist!(
-                line.split(|c: char| !c.is_ascii_alphanumeric())
-                    .filter_map(|s| s.parse::<Node>().ok()),
-                name,
-                l,
-                r
-            );
-            (name, (l, r))
-        })
-        .collect();
+/// Like [`solve`], but for networks with other instructions, node names, or start and end nodes.
+pub fn solve_with_rules<Part: AocPart>(input: &str, rules: &NetworkRules) -> anyhow::Result<usize> {
+    let network = Network::parse(input, rules)?;
 
     if Part::is_one() {
-        solve_a(&steps, nodes)
+        solve_a(&network, rules)
     } else {
-        solve_b(&steps, &nodes)
+        solve_b(&network, rules)
     }
 }
 
+pub fn solve<Part: AocPart>(input: &str) -> usize {
+    solve_with_rules::<Part>(input, &NetworkRules::default()).expect("input should parse")
+}
+
 example_tests! {
     - part one:
         a0: "RL
@@ -330,6 +248,66 @@ ZZZ = (ZZZ, ZZZ)" => 6,
 XXX = (XXX, XXX)" => 6,
 }
 
+#[test]
+fn custom_rules() {
+    let rules = NetworkRules {
+        alphabet: vec!['<', '^', '>'],
+        start: NodePattern::new("start"),
+        end: NodePattern::new("goal"),
+        ghost_start: NodePattern::new("g?"),
+        ghost_end: NodePattern::new("*end"),
+    };
+
+    let input = "^>
+start = (start, loop1, goal)
+loop1 = (start, start, goal)
+goal = (goal, goal, goal)
+g1 = (g1, g1end, g1)
+g1end = (g1, g1, g1)
+g2 = (g2, g2x, g2)
+g2x = (g2, g2, g2y)
+g2y = (g2, g2end, g2)
+g2end = (g2, g2end, g2end)";
+
+    assert_eq!(solve_with_rules::<part::One>(input, &rules).unwrap(), 2);
+    assert_eq!(solve_with_rules::<part::Two>(input, &rules).unwrap(), 3);
+    assert!(solve_with_rules::<part::One>("LR", &rules).is_err());
+
+    // the default patterns match nothing here
+    let unrelated = "L\n\nBBB = (BBB)";
+    let one_branch = NetworkRules {
+        alphabet: vec!['L'],
+        ..NetworkRules::default()
+    };
+    assert!(solve_with_rules::<part::One>(unrelated, &one_branch).is_err());
+    assert!(solve_with_rules::<part::Two>(unrelated, &one_branch).is_err());
+
+    // without instructions, neither part can walk anywhere
+    let no_steps = "\n\nAAA = (ZZZ)\nZZZ = (ZZZ)";
+    assert!(solve_with_rules::<part::One>(no_steps, &one_branch).is_err());
+    assert!(solve_with_rules::<part::Two>(no_steps, &one_branch).is_err());
+
+    // names are whatever is between the punctuation, not just alphanumerics
+    let punctuated = "L\n\nstart-1 = (élan_2)\nélan_2 = (goal.x)\ngoal.x = (goal.x)";
+    let rules = NetworkRules {
+        alphabet: vec!['L'],
+        start: NodePattern::new("start-1"),
+        end: NodePattern::new("goal.x"),
+        ..NetworkRules::default()
+    };
+    assert_eq!(
+        solve_with_rules::<part::One>(punctuated, &rules).unwrap(),
+        2
+    );
+
+    // a start which never reaches an end is an error rather than an endless loop
+    let rules = NetworkRules {
+        end: NodePattern::new("nowhere"),
+        ..rules
+    };
+    assert!(solve_with_rules::<part::One>(punctuated, &rules).is_err());
+}
+
 #[test]
 fn part_one() {
     let _ = aocutil::log::test_subscriber().try_init();
@@ -0,0 +1,227 @@
+use super::*;
+
+/// An interned node name: the index of the name in its [`Network`].
+#[derive(Copy, Clone, Hash, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Node(u32);
+
+impl Node {
+    fn idx(self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// An instruction: the index of the branch to take out of the current node.
+#[derive(Copy, Clone, Hash, Debug, PartialEq, Eq)]
+pub struct Step(usize);
+
+impl Step {
+    pub fn new(branch: usize) -> Self {
+        Self(branch)
+    }
+}
+
+/// A glob matched against whole node names. `?` matches any one character and `*` matches any
+/// run of characters, including an empty one.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NodePattern(pub String);
+
+impl NodePattern {
+    pub fn new(pattern: &str) -> Self {
+        Self(pattern.to_string())
+    }
+
+    pub fn matches(&self, name: &str) -> bool {
+        fn matches_chars(pattern: &[char], name: &[char]) -> bool {
+            match (pattern.split_first(), name.split_first()) {
+                (None, _) => name.is_empty(),
+                (Some(('*', rest)), _) => {
+                    (0..=name.len()).any(|skip| matches_chars(rest, &name[skip..]))
+                }
+                (Some(('?', rest)), Some((_, name_rest))) => matches_chars(rest, name_rest),
+                (Some((c, rest)), Some((d, name_rest))) => c == d && matches_chars(rest, name_rest),
+                (Some(_), None) => false,
+            }
+        }
+
+        matches_chars(&self.0.chars().collect_vec(), &name.chars().collect_vec())
+    }
+}
+
+/// Everything about a network that the puzzle fixes, but which generated networks might not.
+#[derive(Clone, Debug)]
+pub struct NetworkRules {
+    /// The instruction characters, in the order that each node lists the branches they take.
+    pub alphabet: Vec<char>,
+
+    /// Where the walk in part one starts. Should match exactly one node.
+    pub start: NodePattern,
+
+    /// Where the walk in part one ends.
+    pub end: NodePattern,
+
+    /// Where the ghosts in part two start.
+    pub ghost_start: NodePattern,
+
+    /// Where the ghosts in part two end.
+    pub ghost_end: NodePattern,
+}
+
+impl Default for NetworkRules {
+    fn default() -> Self {
+        Self {
+            alphabet: vec!['L', 'R'],
+            start: NodePattern::new("AAA"),
+            end: NodePattern::new("ZZZ"),
+            ghost_start: NodePattern::new("*A"),
+            ghost_end: NodePattern::new("*Z"),
+        }
+    }
+}
+
+/// The parsed puzzle input, with node names interned.
+#[derive(Clone, Debug)]
+pub struct Network {
+    /// The instructions, which are repeated forever.
+    pub steps: Vec<Step>,
+
+    alphabet: Vec<char>,
+
+    /// Indexed by node.
+    names: Vec<String>,
+
+    /// For each node in order, the nodes that each branch out of it leads to.
+    successors: Vec<Node>,
+}
+
+impl Network {
+    pub fn parse<'a>(input: &'a str, rules: &NetworkRules) -> anyhow::Result<Self> {
+        let mut lines = input.lines();
+
+        let steps = lines
+            .next()
+            .context("network should not be empty")?
+            .chars()
+            .map(|c| {
+                let branch = rules.alphabet.iter().position(|&d| d == c);
+                branch
+                    .map(Step::new)
+                    .with_context(|| format!("unknown instruction {c:?}"))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        if steps.is_empty() {
+            bail!("network should have at least one instruction");
+        }
+
+        let mut ids: HashMap<&'a str, Node> = HashMap::new();
+        let mut names: Vec<String> = Vec::new();
+        let mut successors: Vec<Option<Vec<Node>>> = Vec::new();
+
+        let mut intern = |name: &'a str| -> Node {
+            *ids.entry(name).or_insert_with(|| {
+                names.push(name.to_string());
+                Node(names.len() as u32 - 1)
+            })
+        };
+
+        let mut definitions = Vec::new();
+
+        for line in lines.filter(|line| !line.trim().is_empty()) {
+            let (name, branches) = line
+                .split_once('=')
+                .with_context(|| format!("'{line}' should have an '='"))?;
+
+            let branches = branches
+                .trim()
+                .strip_prefix('(')
+                .and_then(|branches| branches.strip_suffix(')'))
+                .with_context(|| format!("branches in '{line}' should be in parentheses"))?;
+
+            let names_in_line = iter::once(name).chain(branches.split(',')).map(str::trim);
+            if names_in_line.clone().any(str::is_empty) {
+                bail!("'{line}' has an empty node name");
+            }
+
+            let mut names_in_line = names_in_line.map(&mut intern);
+            let node = names_in_line.next().unwrap();
+            let branches = names_in_line.collect_vec();
+
+            if branches.len() != rules.alphabet.len() {
+                bail!(
+                    "expected {} branches, found {} in '{line}'",
+                    rules.alphabet.len(),
+                    branches.len()
+                );
+            }
+
+            definitions.push((node, branches));
+        }
+
+        successors.resize(names.len(), None);
+
+        for (node, branches) in definitions {
+            if successors[node.idx()].replace(branches).is_some() {
+                bail!("node {} is defined twice", names[node.idx()]);
+            }
+        }
+
+        let successors = successors
+            .into_iter()
+            .enumerate()
+            .map(|(idx, branches)| {
+                branches.with_context(|| format!("node {} is never defined", names[idx]))
+            })
+            .flatten_ok()
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        Ok(Self {
+            steps,
+            alphabet: rules.alphabet.clone(),
+            names,
+            successors,
+        })
+    }
+
+    pub fn name(&self, node: Node) -> &str {
+        &self.names[node.idx()]
+    }
+
+    pub fn step_char(&self, step: Step) -> char {
+        self.alphabet[step.0]
+    }
+
+    pub fn next(&self, node: Node, step: Step) -> Node {
+        self.successors[node.idx() * self.alphabet.len() + step.0]
+    }
+
+    /// Every node, in the order their names first appear in the input.
+    pub fn nodes(&self) -> impl Iterator<Item = Node> {
+        (0..self.names.len() as u32).map(Node)
+    }
+
+    /// The nodes leading out of `node`, one for each instruction in the alphabet.
+    pub fn branches(&self, node: Node) -> &[Node] {
+        let branches = self.alphabet.len();
+        &self.successors[node.idx() * branches..(node.idx() + 1) * branches]
+    }
+
+    /// Whether each node matches `pattern`, indexed by node.
+    pub fn mask(&self, pattern: &NodePattern) -> NodeMask {
+        NodeMask(
+            self.names
+                .iter()
+                .map(|name| pattern.matches(name))
+                .collect(),
+        )
+    }
+}
+
+/// A set of nodes, precomputed from a [`NodePattern`] so that checking membership is cheap.
+#[derive(Clone, Debug)]
+pub struct NodeMask(Vec<bool>);
+
+impl NodeMask {
+    pub fn contains(&self, node: Node) -> bool {
+        self.0[node.idx()]
+    }
+}
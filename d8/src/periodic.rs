@@ -0,0 +1,140 @@
+use super::*;
+
+use num::Integer;
+
+/// A set of natural numbers which is periodic from some point on, such as the steps on which a
+/// walk around a finite graph is at a certain kind of node.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PeriodicSet {
+    /// The elements less than `threshold`, in increasing order.
+    prefix: Vec<usize>,
+
+    /// From here on, `n` is in the set if and only if `n % period` is in `residues`.
+    threshold: usize,
+
+    period: usize,
+
+    /// In increasing order, and all less than `period`.
+    residues: Vec<usize>,
+}
+
+/// Solves `x = a (mod m)` and `x = b (mod n)` for moduli which need not be coprime. Returns the
+/// solution modulo `lcm(m, n)`, if there is one.
+fn crt(a: usize, m: usize, b: usize, n: usize) -> Option<usize> {
+    let (a, m, b, n) = (a as i128, m as i128, b as i128, n as i128);
+
+    let gcd = m.extended_gcd(&n);
+    if (b - a) % gcd.gcd != 0 {
+        return None;
+    }
+
+    // m * gcd.x = gcd (mod n), so stepping a by m * k with k = (b - a) / gcd * gcd.x lands on b
+    let lcm = m / gcd.gcd * n;
+    let k = ((b - a) / gcd.gcd * gcd.x).rem_euclid(n / gcd.gcd);
+
+    Some((a + m * k).rem_euclid(lcm) as usize)
+}
+
+impl PeriodicSet {
+    /// Panics if `period` is zero.
+    pub fn new(
+        prefix: impl IntoIterator<Item = usize>,
+        threshold: usize,
+        period: usize,
+        residues: impl IntoIterator<Item = usize>,
+    ) -> Self {
+        assert!(period > 0, "period should be positive");
+
+        let prefix: BTreeSet<usize> = prefix.into_iter().filter(|&n| n < threshold).collect();
+        let residues: BTreeSet<usize> = residues.into_iter().map(|r| r % period).collect();
+
+        Self {
+            prefix: prefix.into_iter().collect(),
+            threshold,
+            period,
+            residues: residues.into_iter().collect(),
+        }
+    }
+
+    pub fn period(&self) -> usize {
+        self.period
+    }
+
+    pub fn contains(&self, n: usize) -> bool {
+        if n < self.threshold {
+            self.prefix.binary_search(&n).is_ok()
+        } else {
+            self.residues.binary_search(&(n % self.period)).is_ok()
+        }
+    }
+
+    /// The smallest element which is at least `n`.
+    pub fn next_from(&self, n: usize) -> Option<usize> {
+        if n < self.threshold {
+            let i = self.prefix.partition_point(|&m| m < n);
+            if let Some(&m) = self.prefix.get(i) {
+                return Some(m);
+            }
+        }
+
+        let n = cmp::max(n, self.threshold);
+        let offset = n % self.period;
+
+        // the first residue at or after `n`'s, wrapping around to the next period
+        let i = self.residues.partition_point(|&r| r < offset);
+        match self.residues.get(i) {
+            Some(&r) => Some(n - offset + r),
+            None => Some(n - offset + self.period + self.residues.first()?),
+        }
+    }
+
+    pub fn first(&self) -> Option<usize> {
+        self.next_from(0)
+    }
+
+    /// Every element in increasing order. Infinite unless the set is finite.
+    pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        iter::successors(self.first(), |&n| self.next_from(n + 1))
+    }
+
+    /// The elements in both sets. The period of the result is the least common multiple of the
+    /// two periods.
+    pub fn intersection(&self, other: &Self) -> Self {
+        let threshold = cmp::max(self.threshold, other.threshold);
+
+        let prefix = self
+            .iter()
+            .take_while(|&n| n < threshold)
+            .filter(|&n| other.contains(n))
+            .collect_vec();
+
+        let residues = self
+            .residues
+            .iter()
+            .cartesian_product(&other.residues)
+            .filter_map(|(&a, &b)| crt(a, self.period, b, other.period))
+            .collect_vec();
+
+        Self::new(prefix, threshold, self.period.lcm(&other.period), residues)
+    }
+}
+
+#[test]
+fn intersection() {
+    // n = 1 (mod 4) from 5 on, plus 0 and 2
+    let a = PeriodicSet::new([0, 2], 5, 4, [1]);
+    // n = 3 or 5 (mod 6) from 2 on, plus 0
+    let b = PeriodicSet::new([0], 2, 6, [3, 5]);
+
+    assert_eq!(a.iter().take(5).collect_vec(), [0, 2, 5, 9, 13]);
+    assert_eq!(b.iter().take(5).collect_vec(), [0, 3, 5, 9, 11]);
+
+    let both = a.intersection(&b);
+    assert_eq!(both.period(), 12);
+    assert_eq!(both.iter().take(4).collect_vec(), [0, 5, 9, 17]);
+
+    // n = 0 (mod 4) and n = 1 (mod 6) have no common solutions
+    let c = PeriodicSet::new([], 0, 4, [0]);
+    let d = PeriodicSet::new([], 0, 6, [1]);
+    assert_eq!(c.intersection(&d).first(), None);
+}
@@ -1,49 +1,104 @@
 use aocutil::prelude::*;
 
+use num::{BigInt, One, ToPrimitive, Zero};
+
 const YEAR: usize = 2023;
 
 const DAY: usize = 9;
 
-fn solve<P: AocPart>(input: &str) -> i32 {
+/// A sequence of integers which is a polynomial in its index, so that it can be extended in either
+/// direction.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Sequence {
+    /// How many values the sequence was fitted to.
+    len: usize,
+
+    /// The first entry of each row of the difference pyramid, down to the first constant row.
+    /// These are the coefficients of the polynomial in the Newton forward difference basis.
+    leading_diffs: Vec<BigInt>,
+}
+
+impl FromStr for Sequence {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::fit(ints::<i64>(s))
+    }
+}
+
+impl Sequence {
+    /// Finds the polynomial of least degree through `values`. Fails unless some row of differences
+    /// is constant with at least two entries, since otherwise nothing confirms the fit.
+    pub fn fit(values: impl IntoIterator<Item = impl Into<BigInt>>) -> anyhow::Result<Self> {
+        let mut row: Vec<BigInt> = values.into_iter().map(Into::into).collect();
+        let len = row.len();
+
+        let mut leading_diffs = Vec::new();
+
+        while !row.iter().all_equal() {
+            leading_diffs.push(row[0].clone());
+
+            row = row.iter().tuple_windows().map(|(n, m)| m - n).collect();
+        }
+
+        if row.len() < 2 {
+            bail!("sequence of length {len} never reaches a constant row of differences");
+        }
+
+        leading_diffs.push(row[0].clone());
+
+        Ok(Self { len, leading_diffs })
+    }
+
+    pub fn degree(&self) -> usize {
+        self.leading_diffs.len() - 1
+    }
+
+    /// The value at index `n`, where the first value the sequence was fitted to has index 0. `n`
+    /// may be negative or past the end of the fitted values.
+    pub fn at(&self, n: i64) -> BigInt {
+        let n = BigInt::from(n);
+
+        let mut res = BigInt::zero();
+
+        // the binomial coefficient (n choose k), which stays an integer even for negative n
+        let mut binomial = BigInt::one();
+
+        for (k, diff) in self.leading_diffs.iter().enumerate() {
+            res += diff * &binomial;
+            binomial = binomial * (&n - k) / (k + 1);
+        }
+
+        res
+    }
+
+    /// The value `steps` places after the last fitted value.
+    pub fn forward(&self, steps: usize) -> BigInt {
+        self.at((self.len - 1 + steps) as i64)
+    }
+
+    /// The value `steps` places before the first fitted value.
+    pub fn backward(&self, steps: usize) -> BigInt {
+        self.at(-(steps as i64))
+    }
+}
+
+fn solve<P: AocPart>(input: &str) -> i64 {
     input
         .lines()
         .map(|line| {
-            let mut sequences: Vec<Vec<i32>> = vec![ints(line).collect()];
-            while !sequences.last().unwrap().iter().all_equal() {
-                let diffs = sequences
-                    .last()
-                    .unwrap()
-                    .iter()
-                    .tuple_windows()
-                    .map(|(n, m)| m - n)
-                    .collect();
-                sequences.push(diffs);
-            }
+            let sequence: Sequence = line.parse().expect("line should be a polynomial sequence");
 
-            while sequences.len() > 1 {
-                let tmp = sequences.pop().unwrap();
-                let prev_seq = sequences.last_mut().unwrap();
-
-                match P::part() {
-                    Part::One => {
-                        let diff = tmp.last().unwrap();
-                        let prev_last = prev_seq.last().unwrap();
-                        prev_seq.push(prev_last + diff);
-                    }
-                    Part::Two => {
-                        let diff = tmp[0];
-                        let prev_first = prev_seq[0];
-                        prev_seq.insert(0, prev_first - diff);
-                    }
-                }
-            }
+            trace!("degree {}: {line}", sequence.degree());
 
             match P::part() {
-                Part::One => *sequences[0].last().unwrap(),
-                Part::Two => sequences[0][0],
+                Part::One => sequence.forward(1),
+                Part::Two => sequence.backward(1),
             }
         })
-        .sum()
+        .sum::<BigInt>()
+        .to_i64()
+        .expect("sum should fit in an i64")
 }
 
 const EXAMPLE_INPUT: &str = "0 3 6 9 12 15
@@ -57,6 +112,22 @@ example_tests! {
         b0: EXAMPLE_INPUT => 2,
 }
 
+#[test]
+fn extrapolation() {
+    let sequence: Sequence = "10 13 16 21 30 45".parse().unwrap();
+    assert_eq!(sequence.degree(), 3);
+    assert_eq!(sequence.forward(1), BigInt::from(68));
+    assert_eq!(sequence.backward(1), BigInt::from(5));
+    assert_eq!(sequence.backward(2), BigInt::from(-4));
+
+    // n^3 at n = 10^9
+    let cubes: Sequence = "0 1 8 27 64".parse().unwrap();
+    assert_eq!(cubes.at(1_000_000_000), BigInt::from(10).pow(27));
+
+    assert!("1 2 4".parse::<Sequence>().is_err());
+    assert!("".parse::<Sequence>().is_err());
+}
+
 #[test]
 fn part_one() {
     let _ = aocutil::log::test_subscriber().try_init();